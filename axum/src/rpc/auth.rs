@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use rst_common::standard::async_trait::async_trait;
+use rst_common::standard::serde::de::DeserializeOwned;
+use rst_common::standard::serde::{self, Deserialize, Serialize};
+use rst_common::standard::serde_json::{self, json};
+use rst_common::with_cryptography::hex;
+use rst_common::with_cryptography::sha2::{Digest, Sha256};
+
+use prople_jsonrpc_core::objects::{RpcContext, RpcPrincipal};
+use prople_jsonrpc_core::types::RpcError as CoreError;
+
+use super::RpcError;
+
+/// `DEFAULT_API_KEY_HEADER` is the default header name used to send the API keys
+pub const DEFAULT_API_KEY_HEADER: &str = "x-api-key";
+
+const SHA256_PREFIX: &str = "sha256:";
+
+/// `AuthenticatorBoxed` is an alias type used as shortcut to the shared authenticator type
+pub type AuthenticatorBoxed = Arc<dyn Authenticator + Send + Sync>;
+
+/// `Authenticator` used to resolve the caller of each HTTP request
+///
+/// It will be called once for each HTTP request, before any `JSON-RPC` requests are executed,
+/// using the [`RpcContext`] built from the HTTP request. A failed authentication should be
+/// returned as [`CoreError::Unauthorized`], which will be answered without executing any
+/// requests. The resolved principal will be available to all handlers of the HTTP request
+#[async_trait]
+pub trait Authenticator {
+    async fn authenticate(&self, ctx: &RpcContext) -> Result<RpcPrincipal, CoreError>;
+}
+
+/// `Credentials` are the secrets sent by the client
+#[derive(Debug, Clone, PartialEq)]
+pub enum Credentials {
+    /// Taken from the `Authorization: Bearer <token>` header
+    Bearer(String),
+
+    /// Taken from the API key header
+    ApiKey(String),
+}
+
+impl Credentials {
+    /// `from_context` used to read the credentials from given context headers, the bearer
+    /// token always has higher priority than the API key
+    pub fn from_context(ctx: &RpcContext, api_key_header: &str) -> Option<Self> {
+        let bearer = ctx.header("authorization").and_then(|value| {
+            let (scheme, token) = value.trim().split_once(' ')?;
+            let token = token.trim();
+
+            (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty())
+                .then(|| Credentials::Bearer(token.to_string()))
+        });
+
+        bearer.or_else(|| {
+            ctx.header(api_key_header)
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(|key| Credentials::ApiKey(key.to_string()))
+        })
+    }
+
+    pub fn secret(&self) -> &str {
+        match self {
+            Credentials::Bearer(secret) | Credentials::ApiKey(secret) => secret,
+        }
+    }
+}
+
+/// `ApiKeyEntry` is a single record of the keys file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "self::serde")]
+pub struct ApiKeyEntry {
+    pub id: String,
+    pub secret_hash: String,
+
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+/// `ApiKeysFile` is the content of the keys file
+///
+/// ```json
+/// {
+///     "keys": [
+///         {"id": "vessel-agent", "secret_hash": "sha256:<hex>", "scopes": ["vessel.read"]}
+///     ]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(crate = "self::serde")]
+pub struct ApiKeysFile {
+    pub keys: Vec<ApiKeyEntry>,
+}
+
+/// `ApiKeys` is an [`Authenticator`] based on a local keys file with hashed secrets
+///
+/// The secret may be sent as a bearer token or through the API key header. Only the
+/// hash of the secrets are kept, so the keys file never contains any plain secrets.
+/// The resolved principal id and scopes will be taken from the key entry
+#[derive(Clone)]
+pub struct ApiKeys {
+    keys: HashMap<String, RpcPrincipal>,
+    header: String,
+}
+
+impl ApiKeys {
+    pub fn new(file: ApiKeysFile) -> Result<Self, RpcError> {
+        let mut keys = HashMap::new();
+        for entry in file.keys {
+            let digest = entry
+                .secret_hash
+                .strip_prefix(SHA256_PREFIX)
+                .and_then(|digest| hex::decode(digest).ok())
+                .filter(|digest| digest.len() == 32)
+                .ok_or_else(|| {
+                    RpcError::AuthError(format!("invalid secret hash of key: {}", entry.id))
+                })?;
+
+            let principal = RpcPrincipal::new(&entry.id).with_scopes(&entry.scopes);
+            keys.insert(hex::encode(digest), principal);
+        }
+
+        Ok(Self {
+            keys,
+            header: DEFAULT_API_KEY_HEADER.to_string(),
+        })
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, RpcError> {
+        parse_json(content).and_then(Self::new)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, RpcError> {
+        Self::from_json(&read_file(path.as_ref())?)
+    }
+
+    /// `with_header` used to change the API key header name
+    pub fn with_header(mut self, header: &str) -> Self {
+        self.header = header.to_lowercase();
+        self
+    }
+
+    /// `hash_secret` used to build the `secret_hash` of given secret for the keys file
+    pub fn hash_secret(secret: &str) -> String {
+        format!("{}{}", SHA256_PREFIX, hex::encode(Sha256::digest(secret)))
+    }
+
+    /// `verify` used to find the key id of given secret
+    pub fn verify(&self, secret: &str) -> Option<&str> {
+        self.principal(secret).map(RpcPrincipal::id)
+    }
+
+    fn principal(&self, secret: &str) -> Option<&RpcPrincipal> {
+        let digest = hex::encode(Sha256::digest(secret));
+        self.keys.get(&digest)
+    }
+}
+
+#[async_trait]
+impl Authenticator for ApiKeys {
+    async fn authenticate(&self, ctx: &RpcContext) -> Result<RpcPrincipal, CoreError> {
+        let credentials = Credentials::from_context(ctx, &self.header)
+            .ok_or_else(|| unauthorized("missing credentials"))?;
+
+        self.principal(credentials.secret())
+            .cloned()
+            .ok_or_else(|| unauthorized("invalid credentials"))
+    }
+}
+
+/// `unauthorized` used to build the [`CoreError::Unauthorized`] error of a rejected request
+pub(crate) fn unauthorized(reason: &str) -> CoreError {
+    CoreError::Unauthorized.with_data(json!({"reason": reason}))
+}
+
+/// `parse_json` used to parse the content of the keys files
+pub(crate) fn parse_json<T: DeserializeOwned>(content: &str) -> Result<T, RpcError> {
+    serde_json::from_str(content).map_err(|err| RpcError::AuthError(err.to_string()))
+}
+
+/// `read_file` used to read the content of the keys files
+pub(crate) fn read_file(path: &Path) -> Result<String, RpcError> {
+    std::fs::read_to_string(path).map_err(|err| RpcError::AuthError(err.to_string()))
+}
+
+/// `rejected_reason` used by the tests to take the reason of a rejected request
+#[cfg(test)]
+pub(crate) fn rejected_reason<T>(result: Result<T, CoreError>) -> serde_json::Value {
+    result.err().unwrap().data().unwrap()["reason"].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rst_common::with_tokio::tokio;
+
+    fn build_keys() -> ApiKeys {
+        let file = ApiKeysFile {
+            keys: vec![
+                ApiKeyEntry {
+                    id: String::from("agent-1"),
+                    secret_hash: ApiKeys::hash_secret("secret-1"),
+                    scopes: vec![String::from("vessel.read")],
+                },
+                ApiKeyEntry {
+                    id: String::from("agent-2"),
+                    secret_hash: ApiKeys::hash_secret("secret-2"),
+                    scopes: vec![],
+                },
+            ],
+        };
+
+        ApiKeys::new(file).unwrap()
+    }
+
+    #[test]
+    fn test_credentials_from_context() {
+        let table = vec![
+            (
+                vec![("Authorization", "Bearer abc")],
+                Some(Credentials::Bearer(String::from("abc"))),
+            ),
+            (
+                vec![("authorization", "bearer  abc "), ("x-api-key", "key")],
+                Some(Credentials::Bearer(String::from("abc"))),
+            ),
+            (
+                vec![("authorization", "Basic abc"), ("X-Api-Key", "key")],
+                Some(Credentials::ApiKey(String::from("key"))),
+            ),
+            (vec![("authorization", "Bearer ")], None),
+            (vec![], None),
+        ];
+
+        for (headers, expected) in table {
+            let mut ctx = RpcContext::new();
+            for (name, value) in headers {
+                ctx.insert_header(name, value);
+            }
+
+            assert_eq!(
+                Credentials::from_context(&ctx, DEFAULT_API_KEY_HEADER),
+                expected
+            )
+        }
+    }
+
+    #[test]
+    fn test_api_keys_file() {
+        let hash = ApiKeys::hash_secret("secret");
+        let content = format!(r#"{{"keys":[{{"id":"agent","secret_hash":"{}"}}]}}"#, hash);
+
+        let keys = ApiKeys::from_json(&content).unwrap();
+        assert_eq!(keys.verify("secret"), Some("agent"));
+        assert_eq!(keys.verify("unknown"), None);
+
+        let invalid = ApiKeys::from_json(r#"{"keys":[{"id":"agent","secret_hash":"md5:abc"}]}"#);
+        assert!(invalid.is_err());
+        assert!(ApiKeys::from_file("/unknown/keys.json").is_err())
+    }
+
+    #[tokio::test]
+    async fn test_api_keys_authenticate() {
+        let keys = build_keys().with_header("X-Agent-Key");
+
+        let table = vec![
+            (
+                vec![("authorization", "Bearer secret-1")],
+                Ok(("agent-1", vec!["vessel.read"])),
+            ),
+            (vec![("x-agent-key", "secret-2")], Ok(("agent-2", vec![]))),
+            (vec![("x-api-key", "secret-2")], Err("missing credentials")),
+            (
+                vec![("authorization", "Bearer secret-3")],
+                Err("invalid credentials"),
+            ),
+        ];
+
+        for (headers, expected) in table {
+            let mut ctx = RpcContext::new();
+            for (name, value) in headers {
+                ctx.insert_header(name, value);
+            }
+
+            let result = keys.authenticate(&ctx).await;
+            match expected {
+                Ok((id, scopes)) => {
+                    let principal = result.unwrap();
+                    assert_eq!(principal.id(), id);
+                    assert_eq!(principal.scopes(), scopes)
+                }
+                Err(reason) => {
+                    let err = result.err().unwrap();
+                    assert_eq!(err.build().0, -32004);
+                    assert_eq!(err.data(), Some(json!({"reason": reason})))
+                }
+            }
+        }
+    }
+}
@@ -1,14 +1,27 @@
+use super::RpcTlsConfig;
+
 pub struct Config {
     host: String,
-    port: String
+    port: String,
+    tls: Option<RpcTlsConfig>
 }
 
 impl Config {
     pub fn new(host: String, port: String) -> Self {
-        Self { host, port }
+        Self { host, port, tls: None }
+    }
+
+    /// `with_tls` used to serve the `JSON-RPC` over HTTPS instead of plain HTTP
+    pub fn with_tls(mut self, tls: RpcTlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
     }
 
     pub fn load(&self) -> (String, String) {
         (self.host.clone(), self.port.clone())
     }
+
+    pub fn tls(&self) -> Option<&RpcTlsConfig> {
+        self.tls.as_ref()
+    }
 }
\ No newline at end of file
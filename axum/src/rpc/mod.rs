@@ -1,8 +1,39 @@
+#[allow(clippy::module_inception)]
 mod rpc;
-pub use rpc::{RpcState, Rpc, handler as RpcHandlerFn};
+pub use rpc::{RpcState, Rpc, handler as RpcHandlerFn, DEFAULT_BODY_LIMIT};
 
 mod types;
 pub use types::RpcError;
 
 mod config;
-pub use config::Config as RpcConfig;
\ No newline at end of file
+pub use config::Config as RpcConfig;
+
+mod auth;
+pub use auth::{
+    ApiKeyEntry as RpcApiKeyEntry, ApiKeys as RpcApiKeys, ApiKeysFile as RpcApiKeysFile,
+    Authenticator as RpcAuthenticator, AuthenticatorBoxed as RpcAuthenticatorBoxed,
+    Credentials as RpcCredentials, DEFAULT_API_KEY_HEADER,
+};
+
+mod jwt;
+pub use jwt::{
+    JwtKey as RpcJwtKey, JwtVerifier as RpcJwtVerifier, DEFAULT_JWT_LEEWAY, DEFAULT_SCOPE_CLAIM,
+};
+pub use jsonwebtoken::jwk::JwkSet as RpcJwkSet;
+
+mod signature;
+pub use signature::{
+    SignatureKeyEntry as RpcSignatureKeyEntry, SignatureKeysFile as RpcSignatureKeysFile,
+    SignatureVerifier as RpcSignatureVerifier, DEFAULT_SIGNATURE_WINDOW,
+};
+
+mod tls;
+pub use tls::{ClientCertificate as RpcClientCertificate, TlsConfig as RpcTlsConfig};
+
+mod service;
+pub use service::HttpService as RpcHttpService;
+
+mod status;
+pub use status::{
+    default_status_code, StatusMapper as RpcStatusMapper, StatusPolicy as RpcStatusPolicy,
+};
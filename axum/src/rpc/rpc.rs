@@ -1,53 +1,281 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
-use rst_common::standard::erased_serde::Serialize as ErasedSerialized;
-use rst_common::with_http_tokio::axum::extract::{Json, State};
-use rst_common::with_http_tokio::axum::http::StatusCode;
+use rst_common::standard::futures::StreamExt;
+use rst_common::standard::serde::Serialize;
+use rst_common::standard::serde_json::json;
+use rst_common::with_http_tokio::axum::body::{Body, Bytes, HttpBody};
+use rst_common::with_http_tokio::axum::extract::{ConnectInfo, Extension, Json, State};
+use rst_common::with_http_tokio::axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use rst_common::with_http_tokio::axum::response::{IntoResponse, Response};
 use rst_common::with_http_tokio::axum::{self, Router};
 use rst_common::with_tokio::tokio::net::TcpListener;
 use rst_common::with_tokio::tokio::{self, signal};
 use rst_common::with_tracing::tracing;
 
-use prople_jsonrpc_core::objects::{RpcProcessor, RpcRequest, RpcResponse};
-use prople_jsonrpc_core::types::*;
+use prople_jsonrpc_core::objects::{
+    RpcCancellation, RpcContext, RpcPayloadResponse, RpcProcessor, RpcRegistry, RpcResponse,
+};
+use prople_jsonrpc_core::types::RpcError as CoreError;
 
 use super::RpcError;
-use super::RpcConfig;
+use super::RpcStatusPolicy;
+use super::{RpcAuthenticator, RpcAuthenticatorBoxed, RpcConfig, RpcSignatureVerifier};
+use super::{tls, RpcClientCertificate};
 
+/// `DEFAULT_BODY_LIMIT` is the default maximum size of the request body, it uses the same
+/// limit with the `Axum` body extractors
+pub const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// `RpcState` is the shared state of the `Axum` handler
+///
+/// The processor is stored inside a [`RpcRegistry`], so its routes can be changed
+/// while the server is running through [`RpcState::registry`]
 #[derive(Clone)]
 pub struct RpcState {
-    processor: Arc<RpcProcessor>,
+    registry: RpcRegistry,
+    status_policy: RpcStatusPolicy,
+    authenticator: Option<RpcAuthenticatorBoxed>,
+    signature_verifier: Option<RpcSignatureVerifier>,
+    body_limit: usize,
 }
 
 impl RpcState {
     pub fn new(processor: RpcProcessor) -> Self {
+        Self::from_registry(RpcRegistry::new(processor))
+    }
+
+    pub fn from_registry(registry: RpcRegistry) -> Self {
         Self {
-            processor: Arc::new(processor),
+            registry,
+            status_policy: RpcStatusPolicy::default(),
+            authenticator: None,
+            signature_verifier: None,
+            body_limit: DEFAULT_BODY_LIMIT,
         }
     }
+
+    /// `processor` used to get the snapshot of current [`RpcProcessor`]
+    pub fn processor(&self) -> Arc<RpcProcessor> {
+        self.registry.load()
+    }
+
+    /// `registry` used to get the live [`RpcRegistry`], any changes to it will be applied
+    /// to the next requests without restarting the server
+    pub fn registry(&self) -> RpcRegistry {
+        self.registry.clone()
+    }
+
+    /// `with_status_policy` used to change the HTTP status code policy of the error responses
+    pub fn with_status_policy(mut self, policy: RpcStatusPolicy) -> Self {
+        self.status_policy = policy;
+        self
+    }
+
+    /// `with_authenticator` used to require all HTTP requests to be authenticated
+    ///
+    /// An unauthenticated request will be answered with a single `JSON-RPC` error response
+    /// without executing any of its requests
+    pub fn with_authenticator<A>(mut self, authenticator: A) -> Self
+    where
+        A: RpcAuthenticator + Send + Sync + 'static,
+    {
+        self.authenticator = Some(Arc::new(authenticator));
+        self
+    }
+
+    /// `with_signature_verifier` used to require all HTTP requests to have a valid payload
+    /// signature
+    ///
+    /// The signature will be verified before the authenticator. Without any authenticator,
+    /// the principal of the signing key will be given to the handlers
+    pub fn with_signature_verifier(mut self, verifier: RpcSignatureVerifier) -> Self {
+        self.signature_verifier = Some(verifier);
+        self
+    }
+
+    /// `with_body_limit` used to change the maximum size of the request body read by
+    /// the [`handler`]
+    pub fn with_body_limit(mut self, limit: usize) -> Self {
+        self.body_limit = limit;
+        self
+    }
 }
 
+/// `handler` is the `Axum` handler function used to process both of a single
+/// request object and a batch of request objects
+///
+/// The request body will be read as raw bytes regardless of its `Content-Type`, so an invalid
+/// body will always be answered with a `JSON-RPC` error response object instead of the `Axum`
+/// extractor rejections. A body larger than the body limit will be answered with
+/// `413 Payload Too Large`, while a body that cannot be read will be answered with
+/// `400 Bad Request`, both of them using [`CoreError::InvalidRequest`].
+///
+/// The HTTP status code of a single error response will be decided by the [`RpcStatusPolicy`].
+/// A batch response will always use `200 OK`, since each of its elements
+/// may have different error conditions. If the payload only contains notifications,
+/// it will be answered with `204 No Content` without any body.
+///
+/// All of request headers and the remote address, if the server was started with the
+/// connection info, will be put into the [`RpcContext`] passed to each handler. When the
+/// server was started with mutual TLS, the verified [`RpcClientCertificate`] will be put
+/// into its extensions too
 pub async fn handler(
     State(state): State<Arc<RpcState>>,
-    Json(payload): Json<RpcRequest>,
-) -> (StatusCode, Json<RpcResponse<Box<dyn ErasedSerialized>>>) {
-    let processor = state.processor.clone();
-    let response = processor.execute(payload).await;
-
-    let err = response.error.clone();
-    let status_code = err
-        .clone()
-        .map(|err_obj| err_obj.code)
-        .map(|err_code| match err_code {
-            INVALID_REQUEST_CODE | INVALID_PARAMS_CODE | PARSE_ERROR_CODE => {
-                StatusCode::BAD_REQUEST
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    client_certificate: Option<Extension<RpcClientCertificate>>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    let ctx = build_context(
+        connect_info.map(|ConnectInfo(addr)| addr),
+        client_certificate.map(|Extension(cert)| cert),
+        &headers,
+    );
+
+    match read_body(&state, body, state.body_limit).await {
+        Ok(body) => respond(&state, ctx, &body).await,
+        Err(response) => response,
+    }
+}
+
+/// `read_body` used to read the request body up to given limit, the rejected body will be
+/// answered with a `JSON-RPC` error response object
+pub(crate) async fn read_body(
+    state: &RpcState,
+    body: Body,
+    limit: usize,
+) -> Result<Bytes, Response> {
+    let too_large = || {
+        let err = CoreError::InvalidRequest.with_data(json!({
+            "reason": "request body too large",
+            "limit": limit,
+        }));
+
+        let mut response = reject(state, err);
+        *response.status_mut() = StatusCode::PAYLOAD_TOO_LARGE;
+        response
+    };
+
+    if body.size_hint().lower() > limit as u64 {
+        return Err(too_large());
+    }
+
+    let mut bytes = Vec::new();
+    let mut stream = body.into_data_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|err| {
+            tracing::error!("error read request body: {}", err);
+            let err = CoreError::InvalidRequest.with_data(json!({
+                "reason": "unreadable request body",
+            }));
+            reject(state, err)
+        })?;
+
+        if bytes.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(Bytes::from(bytes))
+}
+
+/// `respond` used to execute the request body and build its HTTP response
+///
+/// The transport will drop this future when the client has disconnected, which will also
+/// drop the handler futures. The cancellation guard makes sure that the handlers will be
+/// notified through their [`RpcContext`] cancellation token when it happens
+pub(crate) async fn respond(state: &RpcState, mut ctx: RpcContext, body: &[u8]) -> Response {
+    let cancellation = RpcCancellation::new();
+    ctx.set_cancellation(cancellation.clone());
+    let guard = cancellation.drop_guard();
+
+    if let Some(verifier) = &state.signature_verifier {
+        match verifier.verify(&ctx, body) {
+            Ok(principal) => {
+                ctx.set_principal(principal);
             }
-            METHOD_NOT_FOUND_CODE => StatusCode::NOT_FOUND,
-            _ => StatusCode::INTERNAL_SERVER_ERROR,
-        })
-        .unwrap_or_else(|| StatusCode::OK);
+            Err(err) => return reject(state, err),
+        }
+    }
+
+    if let Some(authenticator) = &state.authenticator {
+        match authenticator.authenticate(&ctx).await {
+            Ok(principal) => {
+                ctx.set_principal(principal);
+            }
+            Err(err) => return reject(state, err),
+        }
+    }
+
+    let processor = state.registry.load();
+    let output = processor.execute_bytes(ctx, body).await;
+    guard.disarm();
 
-    (status_code, Json(response))
+    let response = match output {
+        Some(response) => response,
+        None => return StatusCode::NO_CONTENT.into_response(),
+    };
+
+    build_response(state, response)
+}
+
+/// `reject` used to answer a request which has been rejected before its execution
+fn reject(state: &RpcState, err: CoreError) -> Response {
+    let err_obj = state.registry.load().error_catalog().build(err);
+    let response: RpcResponse<()> = RpcResponse::with_error(Some(err_obj), None);
+    build_response(state, RpcPayloadResponse::Single(response))
+}
+
+fn build_response<T: Serialize>(state: &RpcState, response: RpcPayloadResponse<T>) -> Response {
+    let mut headers = HeaderMap::new();
+    let status_code = match &response {
+        RpcPayloadResponse::Single(single) => {
+            let status_code = state.status_policy.status_code(single.error.as_ref());
+            if status_code == StatusCode::SERVICE_UNAVAILABLE
+                || status_code == StatusCode::TOO_MANY_REQUESTS
+            {
+                let retry_after = single.error.as_ref().and_then(|err| err.retry_after());
+                if let Some(retry_after) = retry_after {
+                    headers.insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+                }
+            }
+
+            if status_code == StatusCode::UNAUTHORIZED {
+                headers.insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            }
+
+            status_code
+        }
+        RpcPayloadResponse::Batch(_) => StatusCode::OK,
+    };
+
+    (status_code, headers, Json(response)).into_response()
+}
+
+pub(crate) fn build_context(
+    remote_addr: Option<SocketAddr>,
+    client_certificate: Option<RpcClientCertificate>,
+    headers: &HeaderMap,
+) -> RpcContext {
+    let mut ctx = RpcContext::new();
+    if let Some(addr) = remote_addr {
+        ctx.set_remote_addr(addr);
+    }
+
+    if let Some(cert) = client_certificate {
+        ctx.extensions_mut().insert(cert);
+    }
+
+    for (name, value) in headers.iter() {
+        if let Ok(value) = value.to_str() {
+            ctx.insert_header(name.as_str(), value);
+        }
+    }
+
+    ctx
 }
 
 pub struct Rpc{
@@ -65,42 +293,539 @@ impl Rpc {
         }
     }
 
+    /// `serve` used to start the server, it will be served over HTTPS when the config has
+    /// the [`RpcTlsConfig`](super::RpcTlsConfig)
     pub async fn serve(&self) -> Result<(), RpcError> {
         let (host, port) = self.config.load();
         tracing::info!("listening at: host:{} | port:{}", host, port);
 
-        let listener = TcpListener::bind(format!("{}:{}", host, port))
-            .await
-            .map_err(|err| RpcError::NetError(err.to_string()))?;
-
         let app = self
             .svc_app
             .clone()
             .with_state(Arc::new(self.state.clone()));
 
-        let _ = axum::serve(listener, app)
-            .with_graceful_shutdown(async {
-                let ctrl_c = async { signal::ctrl_c().await.expect("error Ctrl-C handler") };
+        if let Some(tls_config) = self.config.tls() {
+            let listener = std::net::TcpListener::bind(format!("{}:{}", host, port))
+                .map_err(|err| RpcError::NetError(err.to_string()))?;
 
-                #[cfg(unix)]
-                let terminate = async {
-                    signal::unix::signal(signal::unix::SignalKind::terminate())
-                        .expect("failed to install signal handler")
-                        .recv()
-                        .await;
-                };
+            let handle = axum_server::Handle::new();
+            tokio::spawn({
+                let handle = handle.clone();
+                async move {
+                    shutdown_signal().await;
+                    handle.graceful_shutdown(None);
+                }
+            });
 
-                #[cfg(not(unix))]
-                let terminate = std::future::pending::<()>();
+            return tls::serve(listener, tls_config, app, handle).await;
+        }
 
-                tokio::select! {
-                    _ = ctrl_c => {},
-                    _ = terminate => {},
-                }
-            })
+        let listener = TcpListener::bind(format!("{}:{}", host, port))
             .await
-            .map_err(|err| RpcError::AxumError(err.to_string()))?;
+            .map_err(|err| RpcError::NetError(err.to_string()))?;
+
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .map_err(|err| RpcError::AxumError(err.to_string()))?;
 
         Ok(())
     }
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async { signal::ctrl_c().await.expect("error Ctrl-C handler") };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install signal handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::rpc::{RpcApiKeyEntry, RpcApiKeys, RpcApiKeysFile};
+    use crate::rpc::{RpcSignatureKeyEntry, RpcSignatureKeysFile};
+    use prople_jsonrpc_core::objects::{RpcPrincipal, RpcRateLimiter};
+    use prople_jsonrpc_core::types::{
+        signing_message, RpcError as CoreError, RpcFnHandler, RpcMethod, RpcQuota, RpcRateLimitKey,
+        RpcRoute, RpcSigningAlgorithm, SIGNATURE_HEADER, SIGNATURE_KEY_ID_HEADER,
+        SIGNATURE_NONCE_HEADER, SIGNATURE_TIMESTAMP_HEADER,
+    };
+    use rst_common::standard::serde_json;
+    use rst_common::with_cryptography::{hex, ring::hmac};
+    use rst_common::with_http_tokio::axum::body::{self, Body};
+    use rst_common::with_http_tokio::axum::http::Request;
+    use rst_common::with_http_tokio::axum::routing::post;
+    use tower::ServiceExt;
+
+    fn build_app(state: RpcState) -> Router {
+        Router::new()
+            .route("/rpc", post(handler))
+            .with_state(Arc::new(state))
+    }
+
+    async fn send(request: Request<Body>) -> (StatusCode, String) {
+        send_with_state(RpcState::new(RpcProcessor::default()), request).await
+    }
+
+    async fn send_with_state(state: RpcState, request: Request<Body>) -> (StatusCode, String) {
+        let response = build_app(state).oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_handler_success() {
+        let request = Request::post("/rpc")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1}"#,
+            ))
+            .unwrap();
+
+        let (status, body) = send(request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":1}"#
+        )
+    }
+
+    #[tokio::test]
+    async fn test_handler_body_limit() {
+        let input = r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1}"#;
+        let too_large = r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request","data":{"limit":16,"reason":"request body too large"}},"id":null}"#;
+        let chunks = |chunks: Vec<Result<&'static str, std::io::Error>>| {
+            Body::from_stream(rst_common::standard::futures::stream::iter(chunks))
+        };
+
+        let table = vec![
+            (Body::from(input), StatusCode::PAYLOAD_TOO_LARGE, too_large),
+            (
+                chunks(vec![Ok(&input[..10]), Ok(&input[10..])]),
+                StatusCode::PAYLOAD_TOO_LARGE,
+                too_large,
+            ),
+            (
+                chunks(vec![
+                    Ok("{"),
+                    Err(std::io::Error::other("connection reset")),
+                ]),
+                StatusCode::BAD_REQUEST,
+                r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request","data":{"reason":"unreadable request body"}},"id":null}"#,
+            ),
+        ];
+
+        for (input, expected_status, expected_body) in table {
+            let state = RpcState::new(RpcProcessor::default()).with_body_limit(16);
+            let request = Request::post("/rpc").body(input).unwrap();
+
+            let (status, body) = send_with_state(state, request).await;
+            assert_eq!(status, expected_status);
+            assert_eq!(body, expected_body)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handler_without_content_type() {
+        let request = Request::post("/rpc")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .body(Body::from(
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1}"#,
+            ))
+            .unwrap();
+
+        let (status, body) = send(request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":1}"#
+        )
+    }
+
+    #[tokio::test]
+    async fn test_handler_parse_error() {
+        let request = Request::post("/rpc")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"jsonrpc":"2.0","method""#))
+            .unwrap();
+
+        let (status, body) = send(request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            r#"{"jsonrpc":"2.0","error":{"code":-32700,"message":"Parse error"},"id":null}"#
+        )
+    }
+
+    #[tokio::test]
+    async fn test_handler_invalid_request() {
+        let request = Request::post("/rpc")
+            .body(Body::from(
+                r#"{"jsonrpc":"1.0","method":"prople.vessel.ping","id":1}"#,
+            ))
+            .unwrap();
+
+        let (status, body) = send(request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":1}"#
+        )
+    }
+
+    #[tokio::test]
+    async fn test_handler_notification() {
+        let request = Request::post("/rpc")
+            .body(Body::from(
+                r#"[{"jsonrpc":"2.0","method":"prople.vessel.ping"}]"#,
+            ))
+            .unwrap();
+
+        let (status, body) = send(request).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(body.is_empty())
+    }
+
+    #[tokio::test]
+    async fn test_handler_status_policy_always_ok() {
+        let state =
+            RpcState::new(RpcProcessor::default()).with_status_policy(RpcStatusPolicy::AlwaysOk);
+
+        let request = Request::post("/rpc")
+            .body(Body::from(r#"{"jsonrpc":"2.0","method":"unknown","id":1}"#))
+            .unwrap();
+
+        let (status, body) = send_with_state(state, request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":1}"#
+        )
+    }
+
+    #[tokio::test]
+    async fn test_handler_status_policy_custom() {
+        let policy = RpcStatusPolicy::custom(|_| StatusCode::IM_A_TEAPOT);
+        let state = RpcState::new(RpcProcessor::default()).with_status_policy(policy);
+
+        let request = Request::post("/rpc")
+            .body(Body::from(r#"{"jsonrpc":"2.0","method":"unknown","id":1}"#))
+            .unwrap();
+
+        let (status, _) = send_with_state(state, request).await;
+        assert_eq!(status, StatusCode::IM_A_TEAPOT)
+    }
+
+    #[tokio::test]
+    async fn test_handler_context() {
+        let mut processor = RpcProcessor::new();
+        processor.method("test.ctx", |ctx: RpcContext, _: ()| async move {
+            Ok::<_, prople_jsonrpc_core::types::RpcError>(
+                ctx.header("x-request-id").map(String::from),
+            )
+        });
+
+        let request = Request::post("/rpc")
+            .header("X-Request-Id", "abc")
+            .body(Body::from(
+                r#"{"jsonrpc":"2.0","method":"test.ctx","id":1}"#,
+            ))
+            .unwrap();
+
+        let (status, body) = send_with_state(RpcState::new(processor), request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, r#"{"jsonrpc":"2.0","result":"abc","id":1}"#)
+    }
+
+    #[tokio::test]
+    async fn test_handler_live_registry() {
+        let state = RpcState::new(RpcProcessor::default());
+        let app = build_app(state.clone());
+        let build_request = || {
+            Request::post("/rpc")
+                .body(Body::from(
+                    r#"{"jsonrpc":"2.0","method":"plugin.hello","id":1}"#,
+                ))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(build_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        state.registry().method("plugin.hello", |_: ()| async move {
+            Ok::<_, CoreError>("hello")
+        });
+
+        let response = app.clone().oneshot(build_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        state
+            .registry()
+            .unregister(&RpcMethod::from("plugin.hello"));
+
+        let response = app.oneshot(build_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND)
+    }
+
+    #[tokio::test]
+    async fn test_handler_client_disconnected() {
+        let (sender, receiver) = tokio::sync::oneshot::channel::<RpcCancellation>();
+        let sender = Arc::new(std::sync::Mutex::new(Some(sender)));
+
+        let mut processor = RpcProcessor::new();
+        processor.method("test.slow", move |ctx: RpcContext, _: ()| {
+            let sender = sender.clone();
+            async move {
+                if let Some(sender) = sender.lock().unwrap().take() {
+                    let _ = sender.send(ctx.cancellation().clone());
+                }
+
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                Ok::<_, CoreError>("done")
+            }
+        });
+
+        let app = build_app(RpcState::new(processor));
+        let request = Request::post("/rpc")
+            .body(Body::from(
+                r#"{"jsonrpc":"2.0","method":"test.slow","id":1}"#,
+            ))
+            .unwrap();
+
+        let task = tokio::spawn(app.oneshot(request));
+        let token = receiver.await.unwrap();
+        assert!(!token.is_cancelled());
+
+        task.abort();
+        let _ = task.await;
+        assert!(token.is_cancelled())
+    }
+
+    #[tokio::test]
+    async fn test_handler_server_busy() {
+        let mut processor = RpcProcessor::new();
+        processor.method("test.busy", |_: ()| async move {
+            Err::<(), _>(CoreError::ServerBusy.with_data(serde_json::json!({"retry_after": 5})))
+        });
+
+        let request = Request::post("/rpc")
+            .body(Body::from(
+                r#"{"jsonrpc":"2.0","method":"test.busy","id":1}"#,
+            ))
+            .unwrap();
+
+        let response = build_app(RpcState::new(processor))
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response.headers().get(header::RETRY_AFTER),
+            Some(&HeaderValue::from(5))
+        )
+    }
+
+    #[tokio::test]
+    async fn test_handler_rate_limited() {
+        let mut processor = RpcProcessor::default();
+        processor.middleware(RpcRateLimiter::new(
+            RpcRateLimitKey::RemoteAddr,
+            RpcQuota::per_minute(1),
+        ));
+
+        let app = Router::new()
+            .route("/rpc", post(handler))
+            .with_state(Arc::new(RpcState::new(processor)));
+
+        let mut statuses = Vec::new();
+        for _ in 0..2 {
+            let mut request = Request::post("/rpc")
+                .body(Body::from(
+                    r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1}"#,
+                ))
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo("10.0.0.1:4000".parse::<SocketAddr>().unwrap()));
+
+            let response = app.clone().oneshot(request).await.unwrap();
+            statuses.push((
+                response.status(),
+                response.headers().get(header::RETRY_AFTER).cloned(),
+            ));
+        }
+
+        assert_eq!(
+            statuses,
+            vec![
+                (StatusCode::OK, None),
+                (StatusCode::TOO_MANY_REQUESTS, Some(HeaderValue::from(60)))
+            ]
+        )
+    }
+
+    #[tokio::test]
+    async fn test_handler_authentication() {
+        let keys = RpcApiKeys::new(RpcApiKeysFile {
+            keys: vec![RpcApiKeyEntry {
+                id: String::from("agent-1"),
+                secret_hash: RpcApiKeys::hash_secret("secret"),
+                scopes: vec![String::from("vessel.read")],
+            }],
+        })
+        .unwrap();
+
+        let mut processor = RpcProcessor::new();
+        processor
+            .method("test.whoami", |principal: RpcPrincipal, _: ()| async move {
+                Ok::<_, CoreError>(principal.id().to_string())
+            })
+            .register_route(
+                RpcRoute::new(
+                    RpcMethod::from("test.restart"),
+                    Box::new(RpcFnHandler::new(|_: ()| async move {
+                        Ok::<_, CoreError>("restarted")
+                    })),
+                )
+                .with_scopes(&["vessel.admin"]),
+            );
+
+        let state = RpcState::new(processor).with_authenticator(keys);
+        let body = r#"{"jsonrpc":"2.0","method":"test.whoami","id":1}"#;
+        let restart = r#"{"jsonrpc":"2.0","method":"test.restart","id":1}"#;
+
+        let table = vec![
+            (
+                Some("Bearer secret"),
+                restart,
+                StatusCode::FORBIDDEN,
+                r#"{"jsonrpc":"2.0","error":{"code":-32005,"message":"Forbidden","data":{"missing":["vessel.admin"],"required":["vessel.admin"]}},"id":1}"#,
+            ),
+            (
+                Some("Bearer secret"),
+                body,
+                StatusCode::OK,
+                r#"{"jsonrpc":"2.0","result":"agent-1","id":1}"#,
+            ),
+            (
+                Some("Bearer wrong"),
+                body,
+                StatusCode::UNAUTHORIZED,
+                r#"{"jsonrpc":"2.0","error":{"code":-32004,"message":"Unauthorized","data":{"reason":"invalid credentials"}},"id":null}"#,
+            ),
+            (
+                None,
+                body,
+                StatusCode::UNAUTHORIZED,
+                r#"{"jsonrpc":"2.0","error":{"code":-32004,"message":"Unauthorized","data":{"reason":"missing credentials"}},"id":null}"#,
+            ),
+        ];
+
+        for (authorization, input, expected_status, expected_body) in table {
+            let mut request = Request::post("/rpc");
+            if let Some(authorization) = authorization {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+
+            let (status, body) =
+                send_with_state(state.clone(), request.body(Body::from(input)).unwrap()).await;
+            assert_eq!(status, expected_status);
+            assert_eq!(body, expected_body)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handler_signature() {
+        let verifier = RpcSignatureVerifier::new(RpcSignatureKeysFile {
+            keys: vec![RpcSignatureKeyEntry {
+                id: String::from("agent-1"),
+                algorithm: RpcSigningAlgorithm::HmacSha256,
+                key: hex::encode("secret"),
+                scopes: vec![],
+            }],
+        })
+        .unwrap();
+
+        let mut processor = RpcProcessor::new();
+        processor.method("test.whoami", |principal: RpcPrincipal, _: ()| async move {
+            Ok::<_, CoreError>(principal.id().to_string())
+        });
+
+        let state = RpcState::new(processor).with_signature_verifier(verifier);
+        let body = r#"{"jsonrpc":"2.0","method":"test.whoami","id":1}"#;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let message = signing_message("agent-1", timestamp, "n1", body.as_bytes());
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+        let signature = hex::encode(hmac::sign(&key, &message));
+
+        let table = vec![
+            (
+                StatusCode::OK,
+                r#"{"jsonrpc":"2.0","result":"agent-1","id":1}"#,
+            ),
+            (
+                StatusCode::UNAUTHORIZED,
+                r#"{"jsonrpc":"2.0","error":{"code":-32004,"message":"Unauthorized","data":{"reason":"replayed nonce"}},"id":null}"#,
+            ),
+        ];
+
+        for (expected_status, expected_body) in table {
+            let request = Request::post("/rpc")
+                .header(SIGNATURE_KEY_ID_HEADER, "agent-1")
+                .header(SIGNATURE_NONCE_HEADER, "n1")
+                .header(SIGNATURE_TIMESTAMP_HEADER, timestamp.to_string())
+                .header(SIGNATURE_HEADER, signature.as_str())
+                .body(Body::from(body))
+                .unwrap();
+
+            let (status, body) = send_with_state(state.clone(), request).await;
+            assert_eq!(status, expected_status);
+            assert_eq!(body, expected_body)
+        }
+    }
+
+    #[test]
+    fn test_build_context() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", "abc".parse().unwrap());
+        headers.append("accept", "application/json".parse().unwrap());
+        headers.append("accept", "text/plain".parse().unwrap());
+
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let ctx = build_context(Some(addr), None, &headers);
+
+        assert_eq!(ctx.remote_addr(), Some(addr));
+        assert!(ctx.extensions().is_empty());
+        assert_eq!(ctx.header("X-Request-Id"), Some("abc"));
+        assert_eq!(
+            ctx.header_values("accept"),
+            vec!["application/json", "text/plain"]
+        );
+    }
 }
\ No newline at end of file
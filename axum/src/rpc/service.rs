@@ -0,0 +1,161 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use rst_common::standard::futures::future::BoxFuture;
+use rst_common::with_http_tokio::axum::body::{self, Body, HttpBody};
+use rst_common::with_http_tokio::axum::extract::ConnectInfo;
+use rst_common::with_http_tokio::axum::http::Request;
+use rst_common::with_http_tokio::axum::response::Response;
+use rst_common::with_http_tokio::axum::BoxError;
+use rst_common::with_http_tokio::tower::Service;
+
+use super::rpc::{build_context, read_body, respond, DEFAULT_BODY_LIMIT};
+use super::{RpcClientCertificate, RpcState};
+
+/// `HttpService` used to wrap the [`RpcState`] as a `tower::Service<http::Request<B>>`
+///
+/// It has the same behaviors with the [`super::RpcHandlerFn`], but it can be mounted into
+/// any `tower` compatible HTTP servers without the `Axum` router. A body larger than the
+/// body limit will be answered with `413 Payload Too Large`, while a body that cannot be
+/// read will be answered with `400 Bad Request`, without executing any of its requests.
+///
+/// The remote address will only be available if the request extensions contains the
+/// `Axum` `ConnectInfo<SocketAddr>`
+#[derive(Clone)]
+pub struct HttpService {
+    state: Arc<RpcState>,
+    body_limit: usize,
+}
+
+impl HttpService {
+    pub fn new(state: RpcState) -> Self {
+        Self {
+            state: Arc::new(state),
+            body_limit: DEFAULT_BODY_LIMIT,
+        }
+    }
+
+    /// `with_body_limit` used to change the maximum size of the request body
+    pub fn with_body_limit(mut self, limit: usize) -> Self {
+        self.body_limit = limit;
+        self
+    }
+}
+
+impl<B> Service<Request<B>> for HttpService
+where
+    B: HttpBody<Data = body::Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let state = self.state.clone();
+        let body_limit = self.body_limit;
+
+        Box::pin(async move {
+            let (parts, request_body) = request.into_parts();
+            let remote_addr = parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| *addr);
+
+            let client_certificate = parts.extensions.get::<RpcClientCertificate>().cloned();
+
+            let ctx = build_context(remote_addr, client_certificate, &parts.headers);
+            let response = match read_body(&state, Body::new(request_body), body_limit).await {
+                Ok(bytes) => respond(&state, ctx, &bytes).await,
+                Err(response) => response,
+            };
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rst_common::with_http_tokio::axum::http::StatusCode;
+    use rst_common::with_tokio::tokio;
+
+    use prople_jsonrpc_core::objects::RpcProcessor;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_http_service() {
+        let svc = HttpService::new(RpcState::new(RpcProcessor::default()));
+
+        let table = vec![
+            (
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1}"#,
+                StatusCode::OK,
+                r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":1}"#,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"test.unknown","id":2}"#,
+                StatusCode::NOT_FOUND,
+                r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":2}"#,
+            ),
+        ];
+
+        for (input, expected_status, expected_body) in table {
+            let request = Request::post("/").body(Body::from(input)).unwrap();
+            let response = svc.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), expected_status);
+
+            let bytes = body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(String::from_utf8(bytes.to_vec()).unwrap(), expected_body)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_service_body_limit() {
+        let svc = HttpService::new(RpcState::new(RpcProcessor::default())).with_body_limit(8);
+
+        let request = Request::post("/")
+            .body(Body::from(
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1}"#,
+            ))
+            .unwrap();
+
+        let response = svc.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let bytes = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(bytes.to_vec()).unwrap(),
+            r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request","data":{"limit":8,"reason":"request body too large"}},"id":null}"#
+        );
+
+        let unreadable = Body::from_stream(rst_common::standard::futures::stream::iter(vec![
+            Ok::<_, std::io::Error>("{"),
+            Err(std::io::Error::other("connection reset")),
+        ]));
+        let request = Request::post("/").body(unreadable).unwrap();
+
+        let response = svc.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let bytes = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(bytes.to_vec()).unwrap(),
+            r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request","data":{"reason":"unreadable request body"}},"id":null}"#
+        )
+    }
+}
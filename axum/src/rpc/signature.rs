@@ -0,0 +1,400 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rst_common::standard::serde::{self, Deserialize, Serialize};
+use rst_common::with_cryptography::ed25519_dalek::{Signature, VerifyingKey};
+use rst_common::with_cryptography::hex;
+use rst_common::with_cryptography::ring::hmac;
+
+use prople_jsonrpc_core::objects::{RpcContext, RpcPrincipal};
+use prople_jsonrpc_core::types::{
+    signing_message, RpcError as CoreError, RpcSigningAlgorithm, SIGNATURE_HEADER,
+    SIGNATURE_KEY_ID_HEADER, SIGNATURE_NONCE_HEADER, SIGNATURE_TIMESTAMP_HEADER,
+};
+
+use super::auth::{parse_json, read_file, unauthorized};
+use super::RpcError;
+
+/// `DEFAULT_SIGNATURE_WINDOW` is the default maximum age of a signed request
+pub const DEFAULT_SIGNATURE_WINDOW: Duration = Duration::from_secs(300);
+
+/// `MAX_NONCE_LENGTH` is the maximum length of the request nonces
+const MAX_NONCE_LENGTH: usize = 128;
+
+/// `SignatureKeyEntry` is a single record of the signature keys file
+///
+/// The `key` is the hex encoded shared secret for `hmac-sha256`, or the hex encoded
+/// public key for `ed25519`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "self::serde")]
+pub struct SignatureKeyEntry {
+    pub id: String,
+    pub algorithm: RpcSigningAlgorithm,
+    pub key: String,
+
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+/// `SignatureKeysFile` is the content of the signature keys file
+///
+/// ```json
+/// {
+///     "keys": [
+///         {"id": "vessel-agent", "algorithm": "ed25519", "key": "<hex>", "scopes": ["vessel.read"]}
+///     ]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(crate = "self::serde")]
+pub struct SignatureKeysFile {
+    pub keys: Vec<SignatureKeyEntry>,
+}
+
+enum Key {
+    Hmac(hmac::Key),
+    Ed25519(VerifyingKey),
+}
+
+impl Key {
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self {
+            Key::Hmac(key) => hmac::verify(key, message, signature).is_ok(),
+            Key::Ed25519(key) => Signature::from_slice(signature)
+                .is_ok_and(|signature| key.verify_strict(message, &signature).is_ok()),
+        }
+    }
+}
+
+type NonceKey = (String, String);
+
+/// `Nonces` used to remember the nonces of the verified requests, ordered by their
+/// expiration time so the expired nonces can be dropped from the front
+#[derive(Default)]
+struct Nonces {
+    entries: HashMap<NonceKey, u64>,
+    order: BTreeSet<(u64, NonceKey)>,
+}
+
+impl Nonces {
+    /// `sweep` used to drop the nonces which have been expired, it stops at the first
+    /// nonce which is still valid
+    fn sweep(&mut self, now: u64) {
+        while let Some((expires_at, _)) = self.order.first() {
+            if *expires_at >= now {
+                break;
+            }
+
+            if let Some((_, key)) = self.order.pop_first() {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    /// `insert` used to remember given nonce, it returns `false` if the nonce is still remembered
+    fn insert(&mut self, key: NonceKey, expires_at: u64) -> bool {
+        if self.entries.contains_key(&key) {
+            return false;
+        }
+
+        self.order.insert((expires_at, key.clone()));
+        self.entries.insert(key, expires_at);
+        true
+    }
+}
+
+/// `SignatureVerifier` used to verify the signed request payloads
+///
+/// Each request must be signed using the [`signing_message`] of its raw body, and send
+/// the key id, nonce, timestamp and hex encoded signature through the signature headers.
+/// A request with a timestamp outside of the window will be rejected as stale, while the
+/// nonces will be remembered within the window to reject any replayed requests.
+///
+/// A rejected request will be answered with [`CoreError::Unauthorized`], and the principal
+/// of a verified request will be built from its key id and scopes
+#[derive(Clone)]
+pub struct SignatureVerifier {
+    keys: Arc<HashMap<String, (Key, RpcPrincipal)>>,
+    window: Duration,
+    nonces: Arc<Mutex<Nonces>>,
+}
+
+impl SignatureVerifier {
+    pub fn new(file: SignatureKeysFile) -> Result<Self, RpcError> {
+        let mut keys = HashMap::new();
+        for entry in file.keys {
+            let invalid = || RpcError::AuthError(format!("invalid signature key: {}", entry.id));
+            let bytes = hex::decode(&entry.key).map_err(|_| invalid())?;
+            let key = match entry.algorithm {
+                RpcSigningAlgorithm::HmacSha256 if !bytes.is_empty() => {
+                    Key::Hmac(hmac::Key::new(hmac::HMAC_SHA256, &bytes))
+                }
+                RpcSigningAlgorithm::Ed25519 => bytes
+                    .as_slice()
+                    .try_into()
+                    .ok()
+                    .and_then(|bytes| VerifyingKey::from_bytes(bytes).ok())
+                    .map(Key::Ed25519)
+                    .ok_or_else(invalid)?,
+                _ => return Err(invalid()),
+            };
+
+            let principal = RpcPrincipal::new(&entry.id).with_scopes(&entry.scopes);
+            keys.insert(entry.id, (key, principal));
+        }
+
+        Ok(Self {
+            keys: Arc::new(keys),
+            window: DEFAULT_SIGNATURE_WINDOW,
+            nonces: Arc::new(Mutex::new(Nonces::default())),
+        })
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, RpcError> {
+        parse_json(content).and_then(Self::new)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, RpcError> {
+        Self::from_json(&read_file(path.as_ref())?)
+    }
+
+    /// `with_window` used to change the maximum age of a signed request
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// `verify` used to verify the signature headers of given context against the raw body
+    pub fn verify(&self, ctx: &RpcContext, body: &[u8]) -> Result<RpcPrincipal, CoreError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        self.verify_at(ctx, body, now)
+    }
+
+    fn verify_at(
+        &self,
+        ctx: &RpcContext,
+        body: &[u8],
+        now: u64,
+    ) -> Result<RpcPrincipal, CoreError> {
+        let (Some(key_id), Some(nonce), Some(timestamp), Some(signature)) = (
+            ctx.header(SIGNATURE_KEY_ID_HEADER),
+            ctx.header(SIGNATURE_NONCE_HEADER),
+            ctx.header(SIGNATURE_TIMESTAMP_HEADER),
+            ctx.header(SIGNATURE_HEADER),
+        ) else {
+            return Err(unauthorized("missing signature"));
+        };
+
+        if nonce.is_empty() || nonce.len() > MAX_NONCE_LENGTH {
+            return Err(unauthorized("invalid nonce"));
+        }
+
+        let timestamp: u64 = timestamp
+            .parse()
+            .map_err(|_| unauthorized("invalid timestamp"))?;
+
+        if now.abs_diff(timestamp) > self.window.as_secs() {
+            return Err(unauthorized("stale timestamp"));
+        }
+
+        let (key, principal) = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| unauthorized("unknown key"))?;
+
+        let message = signing_message(key_id, timestamp, nonce, body);
+        let verified =
+            hex::decode(signature).is_ok_and(|signature| key.verify(&message, &signature));
+
+        if !verified {
+            return Err(unauthorized("invalid signature"));
+        }
+
+        self.remember(key_id, nonce, timestamp, now)?;
+        Ok(principal.clone())
+    }
+
+    /// `remember` used to save the nonce of a verified request, the nonce will be expired
+    /// together with its timestamp
+    fn remember(
+        &self,
+        key_id: &str,
+        nonce: &str,
+        timestamp: u64,
+        now: u64,
+    ) -> Result<(), CoreError> {
+        let mut nonces = self
+            .nonces
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        nonces.sweep(now);
+
+        let key = (key_id.to_string(), nonce.to_string());
+        if !nonces.insert(key, timestamp + self.window.as_secs()) {
+            return Err(unauthorized("replayed nonce"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rst_common::standard::serde_json::json;
+    use rst_common::with_cryptography::ed25519_dalek::{Signer, SigningKey};
+
+    use crate::rpc::auth::rejected_reason;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn build_verifier(ed_key: &SigningKey) -> SignatureVerifier {
+        SignatureVerifier::new(SignatureKeysFile {
+            keys: vec![
+                SignatureKeyEntry {
+                    id: String::from("agent-1"),
+                    algorithm: RpcSigningAlgorithm::HmacSha256,
+                    key: hex::encode("secret"),
+                    scopes: vec![String::from("vessel.read")],
+                },
+                SignatureKeyEntry {
+                    id: String::from("agent-2"),
+                    algorithm: RpcSigningAlgorithm::Ed25519,
+                    key: hex::encode(ed_key.verifying_key().as_bytes()),
+                    scopes: vec![],
+                },
+            ],
+        })
+        .unwrap()
+    }
+
+    fn build_context(key_id: &str, nonce: &str, timestamp: u64, signature: &[u8]) -> RpcContext {
+        let mut ctx = RpcContext::new();
+        ctx.insert_header(SIGNATURE_KEY_ID_HEADER, key_id)
+            .insert_header(SIGNATURE_NONCE_HEADER, nonce)
+            .insert_header(SIGNATURE_TIMESTAMP_HEADER, &timestamp.to_string())
+            .insert_header(SIGNATURE_HEADER, &hex::encode(signature));
+        ctx
+    }
+
+    fn hmac_context(nonce: &str, timestamp: u64, body: &[u8]) -> RpcContext {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+        let message = signing_message("agent-1", timestamp, nonce, body);
+        build_context(
+            "agent-1",
+            nonce,
+            timestamp,
+            hmac::sign(&key, &message).as_ref(),
+        )
+    }
+
+    #[test]
+    fn test_signature_verify() {
+        let ed_key = SigningKey::from_bytes(&[7; 32]);
+        let verifier = build_verifier(&ed_key);
+        let body = br#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1}"#;
+
+        let principal = verifier
+            .verify_at(&hmac_context("n1", NOW, body), body, NOW)
+            .unwrap();
+        assert_eq!(principal.id(), "agent-1");
+        assert_eq!(principal.scopes(), &["vessel.read"]);
+
+        let message = signing_message("agent-2", NOW, "n1", body);
+        let ctx = build_context("agent-2", "n1", NOW, &ed_key.sign(&message).to_bytes());
+        assert_eq!(verifier.verify_at(&ctx, body, NOW).unwrap().id(), "agent-2");
+
+        let table = vec![
+            (
+                hmac_context("n1", NOW, body),
+                body.as_slice(),
+                "replayed nonce",
+            ),
+            (
+                hmac_context("n2", NOW, body),
+                b"{}".as_slice(),
+                "invalid signature",
+            ),
+            (
+                hmac_context("n3", NOW - 301, body),
+                body.as_slice(),
+                "stale timestamp",
+            ),
+            (
+                hmac_context("n4", NOW + 301, body),
+                body.as_slice(),
+                "stale timestamp",
+            ),
+            (
+                build_context("agent-3", "n5", NOW, b"sig"),
+                body.as_slice(),
+                "unknown key",
+            ),
+            (RpcContext::new(), body.as_slice(), "missing signature"),
+        ];
+
+        for (ctx, body, expected) in table {
+            assert_eq!(
+                rejected_reason(verifier.verify_at(&ctx, body, NOW)),
+                json!(expected)
+            )
+        }
+
+        let later = NOW + 301;
+        let expired = verifier.verify_at(&hmac_context("n1", later, body), body, later);
+        assert!(expired.is_ok())
+    }
+
+    #[test]
+    fn test_signature_nonces_sweep() {
+        let verifier = build_verifier(&SigningKey::from_bytes(&[7; 32]));
+        let body = b"{}";
+
+        for (nonce, now) in [("n1", NOW), ("n2", NOW + 100), ("n3", NOW + 200)] {
+            assert!(verifier
+                .verify_at(&hmac_context(nonce, now, body), body, now)
+                .is_ok());
+        }
+        assert_eq!(verifier.nonces.lock().unwrap().entries.len(), 3);
+
+        let later = NOW + 401;
+        assert!(verifier
+            .verify_at(&hmac_context("n4", later, body), body, later)
+            .is_ok());
+
+        let nonces = verifier.nonces.lock().unwrap();
+        let remembered: Vec<&str> = nonces
+            .order
+            .iter()
+            .map(|(_, (_, nonce))| nonce.as_str())
+            .collect();
+        assert_eq!(remembered, vec!["n3", "n4"]);
+        assert_eq!(nonces.entries.len(), 2)
+    }
+
+    #[test]
+    fn test_signature_keys_file() {
+        let content = r#"{"keys":[{"id":"agent","algorithm":"hmac-sha256","key":"736563726574"}]}"#;
+        assert!(SignatureVerifier::from_json(content).is_ok());
+
+        let invalid = vec![
+            r#"{"keys":[{"id":"agent","algorithm":"ed25519","key":"abcd"}]}"#,
+            r#"{"keys":[{"id":"agent","algorithm":"hmac-sha256","key":""}]}"#,
+            r#"{"keys":[{"id":"agent","algorithm":"rsa","key":"abcd"}]}"#,
+        ];
+
+        for content in invalid {
+            assert!(SignatureVerifier::from_json(content).is_err())
+        }
+
+        assert!(SignatureVerifier::from_file("/unknown/keys.json").is_err())
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use rst_common::with_http_tokio::axum::http::StatusCode;
+
+use prople_jsonrpc_core::types::*;
+
+/// `StatusMapper` is an alias type used as shortcut to the user-supplied status mapping function
+pub type StatusMapper = Arc<dyn Fn(&RpcErrorBuilder) -> StatusCode + Send + Sync>;
+
+/// `StatusPolicy` used to decide which HTTP status code used for a single error response
+///
+/// A success response and a batch response will always use `200 OK`, while a
+/// payload that only contains notifications will always use `204 No Content`
+#[derive(Clone, Default)]
+pub enum StatusPolicy {
+    /// Always use `200 OK`, which is expected by many off-the-shelf `JSON-RPC` clients
+    AlwaysOk,
+
+    /// Map the error codes using [`default_status_code`]
+    #[default]
+    ErrorMapping,
+
+    /// Map the error codes using user-supplied function
+    Custom(StatusMapper),
+}
+
+impl StatusPolicy {
+    /// `custom` used to build [`StatusPolicy::Custom`] from given function
+    ///
+    /// The function may fallback to [`default_status_code`] for any error codes it doesn't care
+    pub fn custom<F>(mapper: F) -> Self
+    where
+        F: Fn(&RpcErrorBuilder) -> StatusCode + Send + Sync + 'static,
+    {
+        StatusPolicy::Custom(Arc::new(mapper))
+    }
+
+    /// `status_code` used to get the HTTP status code of given error object
+    ///
+    /// A `None` value means there is no error, and it will always give `200 OK`
+    pub fn status_code(&self, err: Option<&RpcErrorBuilder>) -> StatusCode {
+        match (self, err) {
+            (_, None) | (StatusPolicy::AlwaysOk, _) => StatusCode::OK,
+            (StatusPolicy::ErrorMapping, Some(err_obj)) => default_status_code(err_obj),
+            (StatusPolicy::Custom(mapper), Some(err_obj)) => mapper(err_obj),
+        }
+    }
+}
+
+/// `default_status_code` is the default mapping from the `JSON-RPC` error codes to
+/// the HTTP status codes
+pub fn default_status_code(err: &RpcErrorBuilder) -> StatusCode {
+    match err.code {
+        INVALID_REQUEST_CODE | INVALID_PARAMS_CODE | PARSE_ERROR_CODE => StatusCode::BAD_REQUEST,
+        METHOD_NOT_FOUND_CODE => StatusCode::NOT_FOUND,
+        REQUEST_TIMEOUT_CODE => StatusCode::GATEWAY_TIMEOUT,
+        SERVER_BUSY_CODE => StatusCode::SERVICE_UNAVAILABLE,
+        RATE_LIMITED_CODE => StatusCode::TOO_MANY_REQUESTS,
+        UNAUTHORIZED_CODE => StatusCode::UNAUTHORIZED,
+        FORBIDDEN_CODE => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_policy() {
+        let not_found = RpcErrorBuilder::build(RpcError::MethodNotFound);
+        let custom = RpcErrorBuilder::build(RpcError::custom(4001));
+        let timeout = RpcErrorBuilder::build(RpcError::RequestTimeout);
+        let busy = RpcErrorBuilder::build(RpcError::ServerBusy);
+        let limited = RpcErrorBuilder::build(RpcError::RateLimited);
+        let unauthorized = RpcErrorBuilder::build(RpcError::Unauthorized);
+        let forbidden = RpcErrorBuilder::build(RpcError::Forbidden);
+
+        let policy = StatusPolicy::default();
+        assert_eq!(policy.status_code(None), StatusCode::OK);
+        assert_eq!(policy.status_code(Some(&not_found)), StatusCode::NOT_FOUND);
+        assert_eq!(
+            policy.status_code(Some(&timeout)),
+            StatusCode::GATEWAY_TIMEOUT
+        );
+        assert_eq!(
+            policy.status_code(Some(&busy)),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            policy.status_code(Some(&limited)),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(
+            policy.status_code(Some(&unauthorized)),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(policy.status_code(Some(&forbidden)), StatusCode::FORBIDDEN);
+        assert_eq!(
+            policy.status_code(Some(&custom)),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        let policy = StatusPolicy::AlwaysOk;
+        assert_eq!(policy.status_code(Some(&not_found)), StatusCode::OK);
+        assert_eq!(policy.status_code(Some(&custom)), StatusCode::OK);
+
+        let policy = StatusPolicy::custom(|err| match err.code {
+            4001 => StatusCode::PAYMENT_REQUIRED,
+            _ => default_status_code(err),
+        });
+        assert_eq!(policy.status_code(None), StatusCode::OK);
+        assert_eq!(policy.status_code(Some(&not_found)), StatusCode::NOT_FOUND);
+        assert_eq!(
+            policy.status_code(Some(&custom)),
+            StatusCode::PAYMENT_REQUIRED
+        );
+    }
+}
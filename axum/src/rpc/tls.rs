@@ -0,0 +1,528 @@
+use std::io::{self, BufReader};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum_server::accept::{Accept, DefaultAcceptor};
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use axum_server::Handle;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use rst_common::standard::futures::future::BoxFuture;
+use rst_common::with_http_tokio::axum::http::Request;
+use rst_common::with_http_tokio::axum::Router;
+use rst_common::with_http_tokio::tower::Service as TowerService;
+use rst_common::with_tokio::tokio::net::TcpStream;
+
+use prople_jsonrpc_core::objects::{RpcContext, RpcFromContext};
+use prople_jsonrpc_core::types::RpcError as CoreError;
+
+use super::auth::unauthorized;
+use super::RpcError;
+
+/// `TlsConfig` used to serve the `JSON-RPC` over HTTPS
+///
+/// The certificate and private key must be PEM encoded, the certificate file may contain
+/// the full chain. When the client CA is given, all clients will be required to present
+/// a certificate signed by it (mutual TLS)
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    client_ca_path: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    pub fn new<P: AsRef<Path>>(cert_path: P, key_path: P) -> Self {
+        Self {
+            cert_path: cert_path.as_ref().to_path_buf(),
+            key_path: key_path.as_ref().to_path_buf(),
+            client_ca_path: None,
+        }
+    }
+
+    /// `with_client_ca` used to require the client certificates signed by given PEM encoded CA
+    pub fn with_client_ca<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.client_ca_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// `server_config` used to load the certificates and build the `rustls` server config
+    pub fn server_config(&self) -> Result<ServerConfig, RpcError> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let certs = load_certs(&self.cert_path)?;
+        let key = load_key(&self.key_path)?;
+
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|err| RpcError::TlsError(err.to_string()))?;
+
+        let builder = match &self.client_ca_path {
+            Some(path) => {
+                let mut roots = RootCertStore::empty();
+                for cert in load_certs(path)? {
+                    roots
+                        .add(cert)
+                        .map_err(|err| RpcError::TlsError(err.to_string()))?;
+                }
+
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                        .build()
+                        .map_err(|err| RpcError::TlsError(err.to_string()))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        let mut config = builder
+            .with_single_cert(certs, key)
+            .map_err(|err| RpcError::TlsError(err.to_string()))?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+        Ok(config)
+    }
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, RpcError> {
+    let file = std::fs::File::open(path)
+        .map_err(|err| RpcError::TlsError(format!("{}: {}", path.display(), err)))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| RpcError::TlsError(format!("{}: {}", path.display(), err)))?;
+
+    if certs.is_empty() {
+        return Err(RpcError::TlsError(format!(
+            "{}: missing certificates",
+            path.display()
+        )));
+    }
+
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>, RpcError> {
+    let file = std::fs::File::open(path)
+        .map_err(|err| RpcError::TlsError(format!("{}: {}", path.display(), err)))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(|err| RpcError::TlsError(format!("{}: {}", path.display(), err)))?
+        .ok_or_else(|| RpcError::TlsError(format!("{}: missing private key", path.display())))
+}
+
+/// `ClientCertificate` is the verified certificate presented by the client through mutual TLS
+///
+/// It will be put into the [`RpcContext`] extensions of each request sent through the
+/// connection, and it can be used directly as the function handler argument
+///
+/// ```ignore
+/// processor.method("agent.whoami", |cert: RpcClientCertificate, _: ()| async move {
+///     Ok::<_, RpcError>(cert.subject().to_string())
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientCertificate {
+    subject: String,
+    common_name: Option<String>,
+    der: Vec<u8>,
+}
+
+impl ClientCertificate {
+    /// `from_der` used to parse a DER encoded certificate
+    pub fn from_der(der: &[u8]) -> Option<Self> {
+        let (_, cert) = X509Certificate::from_der(der).ok()?;
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(|cn| cn.to_string());
+
+        Some(Self {
+            subject: cert.subject().to_string(),
+            common_name,
+            der: der.to_vec(),
+        })
+    }
+
+    /// `subject` used to get the distinguished name of the certificate subject,
+    /// such as `CN=agent-1, O=prople`
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    pub fn common_name(&self) -> Option<&str> {
+        self.common_name.as_deref()
+    }
+
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+}
+
+impl RpcFromContext for ClientCertificate {
+    fn from_context(ctx: &RpcContext) -> Result<Self, CoreError> {
+        ctx.extensions()
+            .get::<ClientCertificate>()
+            .cloned()
+            .ok_or_else(|| unauthorized("missing client certificate"))
+    }
+}
+
+/// `ClientCertificateAcceptor` used to complete the TLS handshake, and pass the client
+/// certificate of the connection to all of its requests
+#[derive(Clone)]
+pub(crate) struct ClientCertificateAcceptor {
+    inner: RustlsAcceptor<DefaultAcceptor>,
+}
+
+impl ClientCertificateAcceptor {
+    pub(crate) fn new(config: ServerConfig) -> Self {
+        Self {
+            inner: RustlsAcceptor::new(RustlsConfig::from_config(Arc::new(config))),
+        }
+    }
+}
+
+impl<S> Accept<TcpStream, S> for ClientCertificateAcceptor
+where
+    S: Send + 'static,
+{
+    type Stream = TlsStream<TcpStream>;
+    type Service = WithClientCertificate<S>;
+    type Future = BoxFuture<'static, io::Result<(Self::Stream, Self::Service)>>;
+
+    fn accept(&self, stream: TcpStream, service: S) -> Self::Future {
+        let acceptor = self.inner.clone();
+
+        Box::pin(async move {
+            let (stream, service) = acceptor.accept(stream, service).await?;
+            let certificate = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(|cert| ClientCertificate::from_der(cert));
+
+            Ok((
+                stream,
+                WithClientCertificate {
+                    inner: service,
+                    certificate,
+                },
+            ))
+        })
+    }
+}
+
+/// `WithClientCertificate` used to insert the client certificate into the request extensions
+#[derive(Clone)]
+pub(crate) struct WithClientCertificate<S> {
+    inner: S,
+    certificate: Option<ClientCertificate>,
+}
+
+impl<S, B> TowerService<Request<B>> for WithClientCertificate<S>
+where
+    S: TowerService<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        if let Some(certificate) = &self.certificate {
+            request.extensions_mut().insert(certificate.clone());
+        }
+
+        self.inner.call(request)
+    }
+}
+
+/// `serve` used to serve the application over TLS until the handle has been shut down
+pub(crate) async fn serve(
+    listener: std::net::TcpListener,
+    config: &TlsConfig,
+    app: Router,
+    handle: Handle,
+) -> Result<(), RpcError> {
+    let acceptor = ClientCertificateAcceptor::new(config.server_config()?);
+
+    axum_server::from_tcp(listener)
+        .acceptor(acceptor)
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .map_err(|err| RpcError::AxumError(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+    use rustls::pki_types::ServerName;
+    use rustls::ClientConfig;
+    use tokio_rustls::TlsConnector;
+
+    use prople_jsonrpc_client::executor::reqwest::Reqwest;
+    use prople_jsonrpc_client::tls::ClientTls;
+    use prople_jsonrpc_client::types::{Executor, NullValue};
+    use prople_jsonrpc_core::objects::RpcProcessor;
+    use prople_jsonrpc_core::types::RpcId;
+    use rst_common::standard::serde_json::json;
+    use rst_common::with_http_tokio::axum::routing::post;
+    use rst_common::with_tokio::tokio::{self, io::AsyncReadExt, io::AsyncWriteExt};
+
+    use crate::rpc::auth::rejected_reason;
+    use crate::rpc::{RpcHandlerFn, RpcState};
+
+    struct Pki {
+        dir: PathBuf,
+        client_cert: CertificateDer<'static>,
+        client_key: Vec<u8>,
+        ca_cert: CertificateDer<'static>,
+    }
+
+    fn build_pki(name: &str) -> Pki {
+        let dir = std::env::temp_dir().join(format!("prople-tls-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca_params
+            .distinguished_name
+            .push(DnType::CommonName, "prople-ca");
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let server_key = KeyPair::generate().unwrap();
+        let server = CertificateParams::new(vec![String::from("localhost")])
+            .unwrap()
+            .signed_by(&server_key, &ca, &ca_key)
+            .unwrap();
+
+        let client_key = KeyPair::generate().unwrap();
+        let mut client_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        client_params
+            .distinguished_name
+            .push(DnType::CommonName, "agent-1");
+        client_params
+            .distinguished_name
+            .push(DnType::OrganizationName, "prople");
+        let client = client_params.signed_by(&client_key, &ca, &ca_key).unwrap();
+
+        std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+        std::fs::write(dir.join("server.pem"), server.pem()).unwrap();
+        std::fs::write(dir.join("server.key"), server_key.serialize_pem()).unwrap();
+        std::fs::write(dir.join("client.pem"), client.pem()).unwrap();
+        std::fs::write(dir.join("client.key"), client_key.serialize_pem()).unwrap();
+
+        Pki {
+            dir,
+            client_cert: client.der().clone(),
+            client_key: client_key.serialize_der(),
+            ca_cert: ca.der().clone(),
+        }
+    }
+
+    fn build_tls_config(pki: &Pki) -> TlsConfig {
+        TlsConfig::new(pki.dir.join("server.pem"), pki.dir.join("server.key"))
+    }
+
+    fn build_connector(pki: &Pki, with_client_cert: bool) -> TlsConnector {
+        let mut roots = RootCertStore::empty();
+        roots.add(pki.ca_cert.clone()).unwrap();
+
+        let builder =
+            ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_root_certificates(roots);
+
+        let config = if with_client_cert {
+            builder
+                .with_client_auth_cert(
+                    vec![pki.client_cert.clone()],
+                    PrivateKeyDer::Pkcs8(pki.client_key.clone().into()),
+                )
+                .unwrap()
+        } else {
+            builder.with_no_client_auth()
+        };
+
+        TlsConnector::from(Arc::new(config))
+    }
+
+    async fn call(connector: TlsConnector, addr: SocketAddr) -> io::Result<String> {
+        let stream = TcpStream::connect(addr).await?;
+        let server_name = ServerName::try_from("localhost").unwrap();
+        let mut stream = connector.connect(server_name, stream).await?;
+
+        let body = r#"{"jsonrpc":"2.0","method":"agent.whoami","id":1}"#;
+        let request = format!(
+            "POST /rpc HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
+    fn build_app() -> Router {
+        let mut processor = RpcProcessor::new();
+        processor.method(
+            "agent.whoami",
+            |cert: ClientCertificate, _: ()| async move {
+                Ok::<_, CoreError>(cert.subject().to_string())
+            },
+        );
+
+        Router::new()
+            .route("/rpc", post(RpcHandlerFn))
+            .with_state(Arc::new(RpcState::new(processor)))
+    }
+
+    #[test]
+    fn test_tls_config() {
+        let pki = build_pki("config");
+
+        let config = build_tls_config(&pki).server_config().unwrap();
+        assert_eq!(
+            config.alpn_protocols,
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+        );
+        assert!(build_tls_config(&pki)
+            .with_client_ca(pki.dir.join("ca.pem"))
+            .server_config()
+            .is_ok());
+
+        let missing = TlsConfig::new(pki.dir.join("unknown.pem"), pki.dir.join("server.key"));
+        assert!(matches!(
+            missing.server_config(),
+            Err(RpcError::TlsError(_))
+        ));
+
+        let swapped = TlsConfig::new(pki.dir.join("server.key"), pki.dir.join("server.pem"));
+        assert!(matches!(
+            swapped.server_config(),
+            Err(RpcError::TlsError(_))
+        ));
+
+        std::fs::remove_dir_all(&pki.dir).unwrap()
+    }
+
+    #[test]
+    fn test_client_certificate() {
+        let pki = build_pki("certificate");
+
+        let cert = ClientCertificate::from_der(&pki.client_cert).unwrap();
+        assert_eq!(cert.subject(), "CN=agent-1, O=prople");
+        assert_eq!(cert.common_name(), Some("agent-1"));
+        assert_eq!(cert.der(), pki.client_cert.as_ref());
+        assert!(ClientCertificate::from_der(b"invalid").is_none());
+
+        let mut ctx = RpcContext::new();
+        assert_eq!(
+            rejected_reason(ClientCertificate::from_context(&ctx)),
+            json!("missing client certificate")
+        );
+
+        ctx.extensions_mut().insert(cert.clone());
+        assert_eq!(ClientCertificate::from_context(&ctx).unwrap(), cert);
+
+        std::fs::remove_dir_all(&pki.dir).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_serve_mutual_tls() {
+        let pki = build_pki("serve");
+        let config = build_tls_config(&pki).with_client_ca(pki.dir.join("ca.pem"));
+        let app = build_app();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = Handle::new();
+        let server = tokio::spawn({
+            let handle = handle.clone();
+            async move { serve(listener, &config, app, handle).await }
+        });
+
+        let response = call(build_connector(&pki, true), addr).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with(r#"{"jsonrpc":"2.0","result":"CN=agent-1, O=prople","id":1}"#));
+
+        let rejected = call(build_connector(&pki, false), addr).await;
+        assert!(rejected.map_or(true, |response| response.is_empty()));
+
+        handle.shutdown();
+        assert!(server.await.unwrap().is_ok());
+        std::fs::remove_dir_all(&pki.dir).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_serve_client_executor() {
+        let pki = build_pki("executor");
+        let config = build_tls_config(&pki).with_client_ca(pki.dir.join("ca.pem"));
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!(
+            "https://localhost:{}/rpc",
+            listener.local_addr().unwrap().port()
+        );
+        let handle = Handle::new();
+        let server = tokio::spawn({
+            let handle = handle.clone();
+            async move { serve(listener, &config, build_app(), handle).await }
+        });
+
+        let tls = ClientTls::new()
+            .with_root_ca_file(pki.dir.join("ca.pem"))
+            .unwrap()
+            .with_identity_files(pki.dir.join("client.pem"), pki.dir.join("client.key"))
+            .unwrap();
+
+        let response = Reqwest::<String>::new()
+            .with_tls(&tls)
+            .unwrap()
+            .call(
+                endpoint.clone(),
+                None::<NullValue>,
+                String::from("agent.whoami"),
+                Some(RpcId::IntegerVal(1)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.result.unwrap(), "CN=agent-1, O=prople");
+
+        let anonymous = ClientTls::new()
+            .with_root_ca_file(pki.dir.join("ca.pem"))
+            .unwrap();
+        let rejected = Reqwest::<String>::new()
+            .with_tls(&anonymous)
+            .unwrap()
+            .call(
+                endpoint,
+                None::<NullValue>,
+                String::from("agent.whoami"),
+                Some(RpcId::IntegerVal(1)),
+            )
+            .await;
+        assert!(rejected.is_err());
+
+        handle.shutdown();
+        assert!(server.await.unwrap().is_ok());
+        std::fs::remove_dir_all(&pki.dir).unwrap()
+    }
+}
@@ -6,5 +6,11 @@ pub enum RpcError {
     NetError(String),
 
     #[error("axum error: {0}")]
-    AxumError(String)
+    AxumError(String),
+
+    #[error("auth error: {0}")]
+    AuthError(String),
+
+    #[error("tls error: {0}")]
+    TlsError(String)
 } 
\ No newline at end of file
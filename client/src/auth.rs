@@ -0,0 +1,177 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rst_common::standard::async_trait::async_trait;
+use rst_common::standard::futures::future::BoxFuture;
+use rst_common::with_tokio::tokio::sync::Mutex;
+
+use crate::types::ExecutorError;
+
+/// `DEFAULT_REFRESH_SKEW` is how long before its expiration a cached token will be refreshed
+pub const DEFAULT_REFRESH_SKEW: Duration = Duration::from_secs(30);
+
+/// `TokenProvider` used by the executors to get the bearer token of each request
+#[async_trait]
+pub trait TokenProvider: Send + Sync {
+    async fn token(&self) -> Result<String, ExecutorError>;
+
+    /// `invalidate` used to drop any cached token, it will be called when the server
+    /// rejected the token with `401 Unauthorized`
+    async fn invalidate(&self) {}
+}
+
+/// `TokenProviderBoxed` is an alias type used as shortcut to the shared token provider type
+pub type TokenProviderBoxed = Arc<dyn TokenProvider>;
+
+/// `StaticToken` is a [`TokenProvider`] which always gives the same token
+#[derive(Clone)]
+pub struct StaticToken(String);
+
+impl StaticToken {
+    pub fn new(token: &str) -> Self {
+        Self(token.to_string())
+    }
+}
+
+#[async_trait]
+impl TokenProvider for StaticToken {
+    async fn token(&self) -> Result<String, ExecutorError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// `Token` is an issued token, including for its lifetime if it has one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub value: String,
+    pub expires_in: Option<Duration>,
+}
+
+impl Token {
+    pub fn new(value: &str, expires_in: Option<Duration>) -> Self {
+        Self {
+            value: value.to_string(),
+            expires_in,
+        }
+    }
+}
+
+type TokenFetcher = Arc<dyn Fn() -> BoxFuture<'static, Result<Token, ExecutorError>> + Send + Sync>;
+
+struct CachedToken {
+    value: String,
+    expires_at: Option<Instant>,
+}
+
+/// `RefreshingToken` is a [`TokenProvider`] which caches the token given by its fetcher,
+/// and fetches a new one when the cached token is about to expire or has been invalidated
+///
+/// Concurrent requests will wait for the same refresh instead of fetching their own tokens
+#[derive(Clone)]
+pub struct RefreshingToken {
+    fetcher: TokenFetcher,
+    cached: Arc<Mutex<Option<CachedToken>>>,
+    skew: Duration,
+}
+
+impl RefreshingToken {
+    pub fn new<F, Fut>(fetcher: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Token, ExecutorError>> + Send + 'static,
+    {
+        Self {
+            fetcher: Arc::new(move || Box::pin(fetcher())),
+            cached: Arc::new(Mutex::new(None)),
+            skew: DEFAULT_REFRESH_SKEW,
+        }
+    }
+
+    /// `with_skew` used to change how long before its expiration a token will be refreshed
+    pub fn with_skew(mut self, skew: Duration) -> Self {
+        self.skew = skew;
+        self
+    }
+}
+
+#[async_trait]
+impl TokenProvider for RefreshingToken {
+    async fn token(&self) -> Result<String, ExecutorError> {
+        let mut cached = self.cached.lock().await;
+        if let Some(token) = cached.as_ref() {
+            let fresh = token
+                .expires_at
+                .map_or(true, |expires_at| Instant::now() + self.skew < expires_at);
+
+            if fresh {
+                return Ok(token.value.clone());
+            }
+        }
+
+        let token = (self.fetcher)().await?;
+        *cached = Some(CachedToken {
+            value: token.value.clone(),
+            expires_at: token
+                .expires_in
+                .map(|expires_in| Instant::now() + expires_in),
+        });
+
+        Ok(token.value)
+    }
+
+    async fn invalidate(&self) {
+        self.cached.lock().await.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use rst_common::with_tokio::tokio;
+
+    fn build_provider(expires_in: Option<Duration>) -> (RefreshingToken, Arc<AtomicUsize>) {
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let provider = RefreshingToken::new({
+            let fetched = fetched.clone();
+            move || {
+                let count = fetched.fetch_add(1, Ordering::SeqCst) + 1;
+                async move { Ok(Token::new(&format!("token-{}", count), expires_in)) }
+            }
+        });
+
+        (provider, fetched)
+    }
+
+    #[tokio::test]
+    async fn test_refreshing_token_cache() {
+        let (provider, fetched) = build_provider(Some(Duration::from_secs(3600)));
+
+        assert_eq!(provider.token().await.unwrap(), "token-1");
+        assert_eq!(provider.token().await.unwrap(), "token-1");
+        assert_eq!(fetched.load(Ordering::SeqCst), 1);
+
+        provider.invalidate().await;
+        assert_eq!(provider.token().await.unwrap(), "token-2");
+        assert_eq!(fetched.load(Ordering::SeqCst), 2)
+    }
+
+    #[tokio::test]
+    async fn test_refreshing_token_expiration() {
+        let (provider, fetched) = build_provider(Some(Duration::from_secs(10)));
+        let provider = provider.with_skew(Duration::from_secs(20));
+
+        assert_eq!(provider.token().await.unwrap(), "token-1");
+        assert_eq!(provider.token().await.unwrap(), "token-2");
+        assert_eq!(fetched.load(Ordering::SeqCst), 2);
+
+        let failing = RefreshingToken::new(|| async {
+            Err(ExecutorError::TokenError(String::from("offline")))
+        });
+        assert!(failing.token().await.is_err());
+        assert_eq!(StaticToken::new("static").token().await.unwrap(), "static")
+    }
+}
@@ -1,13 +1,18 @@
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::sync::Arc;
 
 use rst_common::standard::async_trait::async_trait;
-use rst_common::standard::reqwest::{Client, StatusCode};
+use rst_common::standard::reqwest::{header, Client, Response, StatusCode};
 use rst_common::standard::serde::de::DeserializeOwned;
+use rst_common::standard::serde_json;
 
 use prople_jsonrpc_core::objects::RpcRequest;
 use prople_jsonrpc_core::types::RpcId;
 
+use crate::auth::{TokenProvider, TokenProviderBoxed};
+use crate::signing::RequestSigner;
+use crate::tls::ClientTls;
 use crate::types::{Executor, ExecutorError, JSONResponse, RpcValue};
 
 #[derive(Clone)]
@@ -16,6 +21,8 @@ where
     T: Clone,
 {
     client: Client,
+    token_provider: Option<TokenProviderBoxed>,
+    signer: Option<RequestSigner>,
     _phantom0: PhantomData<T>,
 }
 
@@ -23,12 +30,81 @@ impl<T> Reqwest<T>
 where
     T: Clone,
 {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             client: Client::new(),
-            _phantom0: PhantomData::default(),
+            token_provider: None,
+            signer: None,
+            _phantom0: PhantomData,
         }
     }
+
+    /// `with_token_provider` used to attach a bearer token to each request
+    ///
+    /// When the server rejects the token with `401 Unauthorized`, the token will be
+    /// invalidated and the request will be sent once more with a new token
+    pub fn with_token_provider<P>(mut self, provider: P) -> Self
+    where
+        P: TokenProvider + 'static,
+    {
+        self.token_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// `with_tls` used to rebuild the HTTP client with given root CAs and client certificate
+    pub fn with_tls(mut self, tls: &ClientTls) -> Result<Self, ExecutorError> {
+        self.client = tls
+            .apply(Client::builder())
+            .build()
+            .map_err(|err| ExecutorError::TlsError(err.to_string()))?;
+        Ok(self)
+    }
+
+    /// `with_signer` used to sign the body of each request, a retried request will be signed
+    /// again using a new nonce
+    pub fn with_signer(mut self, signer: RequestSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    async fn send(
+        &self,
+        endpoint: &str,
+        request: &RpcRequest,
+    ) -> Result<Response, ExecutorError> {
+        let body = serde_json::to_vec(request)
+            .map_err(|err| ExecutorError::BuildValueError(err.to_string()))?;
+
+        let mut builder = self
+            .client
+            .post(endpoint)
+            .header(header::CONTENT_TYPE, "application/json");
+
+        if let Some(signer) = self.signer.as_ref() {
+            for (name, value) in signer.sign(&body) {
+                builder = builder.header(name, value);
+            }
+        }
+
+        if let Some(provider) = self.token_provider.as_ref() {
+            builder = builder.bearer_auth(provider.token().await?);
+        }
+
+        builder.body(body).send().await.map_err(|err| {
+            let code = {
+                match err.status() {
+                    Some(code) => code.as_u16(),
+                    _ => StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                }
+            };
+
+            ExecutorError::RequestError {
+                url: endpoint.to_string(),
+                code,
+            }
+        })
+    }
 }
 
 #[async_trait]
@@ -43,41 +119,22 @@ where
         method: String,
         id: Option<RpcId>,
     ) -> Result<JSONResponse<T>, ExecutorError> {
-        let value_params = params.map(|val| {
-            let value = val.build_serde_value();
-            if value.is_err() {
-                return None
-            }
-
-            return Some(value.unwrap()) 
-        }).flatten();
+        let value_params = params.and_then(|val| val.build_serde_value().ok());
 
         let request = RpcRequest {
             jsonrpc: String::from("2.0"),
-            method: String::from(method),
+            method,
             params: value_params,
             id,
         };
 
-        let res = self
-            .client
-            .post(endpoint.clone())
-            .json(&request)
-            .send()
-            .await
-            .map_err(|err| {
-                let code = {
-                    match err.status() {
-                        Some(code) => code.as_u16(),
-                        _ => StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
-                    }
-                };
-
-                ExecutorError::RequestError {
-                    url: endpoint,
-                    code,
-                }
-            })?;
+        let mut res = self.send(&endpoint, &request).await?;
+        if let Some(provider) = self.token_provider.as_ref() {
+            if res.status() == StatusCode::UNAUTHORIZED {
+                provider.invalidate().await;
+                res = self.send(&endpoint, &request).await?;
+            }
+        }
 
         let resp_json = res
             .json::<JSONResponse<T>>()
@@ -95,9 +152,19 @@ mod tests {
     use super::*;
     use mockito::{Matcher, Server};
 
-    use prople_jsonrpc_core::types::{RpcError, RpcErrorBuilder};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::auth::{RefreshingToken, Token};
+    use crate::types::NullValue;
+
+    use prople_jsonrpc_core::types::{
+        signing_message, RpcError, RpcErrorBuilder, SIGNATURE_HEADER, SIGNATURE_KEY_ID_HEADER,
+        SIGNATURE_NONCE_HEADER, SIGNATURE_TIMESTAMP_HEADER,
+    };
     use rst_common::standard::serde::{self, Deserialize, Serialize};
     use rst_common::standard::serde_json::{self, Value};
+    use rst_common::with_cryptography::hex;
+    use rst_common::with_cryptography::ring::hmac;
     use rst_common::with_errors::thiserror::{self, Error};
     use rst_common::with_tokio::tokio;
 
@@ -126,6 +193,7 @@ mod tests {
         }
     }
 
+    #[allow(dead_code)]
     #[derive(Serialize, Deserialize, Error, Debug)]
     #[serde(crate = "self::serde")]
     enum FakeError {
@@ -140,7 +208,7 @@ mod tests {
         };
 
         let try_jsonvalue = serde_json::to_value(payload.clone());
-        assert!(!try_jsonvalue.is_err());
+        assert!(try_jsonvalue.is_ok());
 
         let jsonvalue = try_jsonvalue.unwrap();
         let request_payload = RpcRequest {
@@ -161,7 +229,7 @@ mod tests {
         };
 
         let jsonresp_str_builder = serde_json::to_string(&jsonresp);
-        assert!(!jsonresp_str_builder.is_err());
+        assert!(jsonresp_str_builder.is_ok());
 
         let mut server = Server::new_async().await;
         let mock = server
@@ -186,7 +254,7 @@ mod tests {
             )
             .await;
 
-        assert!(!resp.is_err());
+        assert!(resp.is_ok());
         mock.assert();
 
         let resp_json = resp.unwrap().result;
@@ -203,7 +271,7 @@ mod tests {
         };
 
         let try_jsonvalue = serde_json::to_value(payload.clone());
-        assert!(!try_jsonvalue.is_err());
+        assert!(try_jsonvalue.is_ok());
 
         let jsonvalue = try_jsonvalue.unwrap();
         let request_payload = RpcRequest {
@@ -226,7 +294,7 @@ mod tests {
         };
 
         let jsonresp_str_builder = serde_json::to_string(&jsonresp);
-        assert!(!jsonresp_str_builder.is_err());
+        assert!(jsonresp_str_builder.is_ok());
 
         let mut server = Server::new_async().await;
         let mock = server
@@ -251,7 +319,7 @@ mod tests {
             )
             .await;
 
-        assert!(!resp.is_err());
+        assert!(resp.is_ok());
         mock.assert();
 
         let error_resp = resp.unwrap().error;
@@ -264,6 +332,200 @@ mod tests {
         assert_eq!(error_msg, error_rpc.message);
     }
 
+    #[tokio::test]
+    async fn test_call_error_with_data() {
+        let payload = FakePayload {
+            msg: "hello world".to_string(),
+        };
+
+        let error_data = FakeErrorData {
+            err_msg: "msg is too long".to_string(),
+        };
+
+        let error_response = RpcErrorBuilder::build(
+            RpcError::InvalidParams.with_data(serde_json::to_value(error_data.clone()).unwrap()),
+        );
+
+        let jsonresp: JSONResponse<FakeResponse> = JSONResponse {
+            error: Some(error_response),
+            id: Some(RpcId::IntegerVal(1)),
+            jsonrpc: String::from("2.0"),
+            result: None,
+        };
+
+        let jsonresp_str_builder = serde_json::to_string(&jsonresp);
+        assert!(jsonresp_str_builder.is_ok());
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/rpc")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(jsonresp_str_builder.unwrap())
+            .create_async()
+            .await;
+
+        let url = server.url();
+        let endpoint = format!("{}/rpc", url);
+
+        let client = Reqwest::<FakeResponse>::new();
+        let resp = client
+            .call(
+                endpoint,
+                Some(payload),
+                "test.rpc".to_string(),
+                Some(RpcId::IntegerVal(1)),
+            )
+            .await;
+
+        assert!(resp.is_ok());
+        mock.assert();
+
+        let error_resp = resp.unwrap().error;
+        assert!(error_resp.is_some());
+
+        let error_rpc = error_resp.unwrap();
+        assert_eq!(error_rpc.code, RpcError::InvalidParams.build().0);
+        assert!(error_rpc.data.is_some());
+
+        let data: FakeErrorData = serde_json::from_value(error_rpc.data.unwrap()).unwrap();
+        assert_eq!(data.err_msg, error_data.err_msg)
+    }
+
+    #[tokio::test]
+    async fn test_call_with_token_provider() {
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let provider = RefreshingToken::new({
+            let fetched = fetched.clone();
+            move || {
+                let count = fetched.fetch_add(1, Ordering::SeqCst) + 1;
+                async move { Ok(Token::new(&format!("token-{}", count), None)) }
+            }
+        });
+
+        let jsonresp: JSONResponse<FakeResponse> = JSONResponse {
+            id: Some(RpcId::IntegerVal(1)),
+            result: Some(FakeResponse {
+                msg: "hello response".to_string(),
+            }),
+            error: None,
+            jsonrpc: String::from("2.0"),
+        };
+
+        let mut server = Server::new_async().await;
+        let rejected = server
+            .mock("POST", "/rpc")
+            .match_header("authorization", "Bearer token-1")
+            .with_status(401)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc":"2.0","error":{"code":-32004,"message":"Unauthorized"},"id":null}"#)
+            .create_async()
+            .await;
+
+        let accepted = server
+            .mock("POST", "/rpc")
+            .match_header("authorization", "Bearer token-2")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::to_string(&jsonresp).unwrap())
+            .expect(2)
+            .create_async()
+            .await;
+
+        let endpoint = format!("{}/rpc", server.url());
+        let client = Reqwest::<FakeResponse>::new().with_token_provider(provider);
+
+        for _ in 0..2 {
+            let resp = client
+                .call(
+                    endpoint.clone(),
+                    Some(NullValue),
+                    "test.rpc".to_string(),
+                    Some(RpcId::IntegerVal(1)),
+                )
+                .await;
+
+            assert_eq!(resp.unwrap().result.unwrap().msg, "hello response");
+        }
+
+        rejected.assert();
+        accepted.assert();
+        assert_eq!(fetched.load(Ordering::SeqCst), 2)
+    }
+
+    #[tokio::test]
+    async fn test_call_with_signer() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/rpc")
+            .match_header("x-signature-key-id", "agent-1")
+            .match_header(
+                "x-signature-nonce",
+                Matcher::Regex(String::from("^[0-9a-f]{32}$")),
+            )
+            .match_request(|request| {
+                let header = |name: &str| {
+                    request
+                        .header(name)
+                        .first()
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string()
+                };
+
+                let (Ok(timestamp), Ok(body)) = (
+                    header(SIGNATURE_TIMESTAMP_HEADER).parse::<u64>(),
+                    request.body(),
+                ) else {
+                    return false;
+                };
+
+                let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+                let message = signing_message(
+                    &header(SIGNATURE_KEY_ID_HEADER),
+                    timestamp,
+                    &header(SIGNATURE_NONCE_HEADER),
+                    body,
+                );
+
+                hex::decode(header(SIGNATURE_HEADER))
+                    .is_ok_and(|signature| hmac::verify(&key, &message, &signature).is_ok())
+            })
+            .expect(1)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc":"2.0","result":{"msg":"signed"},"id":1}"#)
+            .create_async()
+            .await;
+
+        let endpoint = format!("{}/rpc", server.url());
+        let client = Reqwest::<FakeResponse>::new()
+            .with_signer(RequestSigner::hmac("agent-1", b"secret"));
+
+        let resp = client
+            .call(
+                endpoint.clone(),
+                Some(NullValue),
+                "test.rpc".to_string(),
+                Some(RpcId::IntegerVal(1)),
+            )
+            .await;
+        assert_eq!(resp.unwrap().result.unwrap().msg, "signed");
+
+        let forged = Reqwest::<FakeResponse>::new()
+            .with_signer(RequestSigner::hmac("agent-1", b"wrong"))
+            .call(
+                endpoint,
+                Some(NullValue),
+                "test.rpc".to_string(),
+                Some(RpcId::IntegerVal(1)),
+            )
+            .await;
+        assert!(forged.is_err());
+
+        mock.assert()
+    }
+
     #[tokio::test]
     async fn test_call_parse_invalid_response() {
         let payload = FakePayload {
@@ -271,7 +533,7 @@ mod tests {
         };
 
         let try_jsonvalue = serde_json::to_value(payload.clone());
-        assert!(!try_jsonvalue.is_err());
+        assert!(try_jsonvalue.is_ok());
 
         let jsonvalue = try_jsonvalue.unwrap();
         let request_payload = RpcRequest {
@@ -1,4 +1,7 @@
 #![doc = include_str!("../README.md")]
 
+pub mod auth;
 pub mod executor;
+pub mod signing;
+pub mod tls;
 pub mod types;
@@ -0,0 +1,165 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rst_common::standard::serde::{self, Deserialize, Serialize};
+use rst_common::standard::serde_json;
+use rst_common::with_cryptography::ed25519_dalek::{Signer, SigningKey};
+use rst_common::with_cryptography::hex;
+use rst_common::with_cryptography::rand;
+use rst_common::with_cryptography::ring::hmac;
+
+use prople_jsonrpc_core::types::{
+    signing_message, RpcSigningAlgorithm, SIGNATURE_HEADER, SIGNATURE_KEY_ID_HEADER,
+    SIGNATURE_NONCE_HEADER, SIGNATURE_TIMESTAMP_HEADER,
+};
+
+use crate::types::{read_file, ExecutorError};
+
+/// `SigningKeyFile` is the content of the signing key file
+///
+/// The `key` is the hex encoded shared secret for `hmac-sha256`, or the hex encoded
+/// 32 bytes secret key for `ed25519`
+///
+/// ```json
+/// {"id": "vessel-agent", "algorithm": "ed25519", "key": "<hex>"}
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "self::serde")]
+pub struct SigningKeyFile {
+    pub id: String,
+    pub algorithm: RpcSigningAlgorithm,
+    pub key: String,
+}
+
+enum Key {
+    Hmac(hmac::Key),
+    Ed25519(SigningKey),
+}
+
+/// `RequestSigner` used to sign the request payloads sent by the executors
+///
+/// Each request will be signed with a new random nonce and current timestamp, and the
+/// signature will cover the exact bytes of the request body
+#[derive(Clone)]
+pub struct RequestSigner {
+    id: String,
+    key: Arc<Key>,
+}
+
+impl RequestSigner {
+    pub fn hmac(id: &str, secret: &[u8]) -> Self {
+        Self {
+            id: id.to_string(),
+            key: Arc::new(Key::Hmac(hmac::Key::new(hmac::HMAC_SHA256, secret))),
+        }
+    }
+
+    pub fn ed25519(id: &str, secret: &[u8; 32]) -> Self {
+        Self {
+            id: id.to_string(),
+            key: Arc::new(Key::Ed25519(SigningKey::from_bytes(secret))),
+        }
+    }
+
+    pub fn new(file: SigningKeyFile) -> Result<Self, ExecutorError> {
+        let invalid = || ExecutorError::SignerError(format!("invalid signing key: {}", file.id));
+        let bytes = hex::decode(&file.key).map_err(|_| invalid())?;
+
+        match file.algorithm {
+            RpcSigningAlgorithm::HmacSha256 if !bytes.is_empty() => {
+                Ok(Self::hmac(&file.id, &bytes))
+            }
+            RpcSigningAlgorithm::Ed25519 => bytes
+                .as_slice()
+                .try_into()
+                .map(|secret| Self::ed25519(&file.id, secret))
+                .map_err(|_| invalid()),
+            _ => Err(invalid()),
+        }
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, ExecutorError> {
+        let file: SigningKeyFile = serde_json::from_str(content)
+            .map_err(|err| ExecutorError::SignerError(err.to_string()))?;
+        Self::new(file)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ExecutorError> {
+        let content = read_file(path.as_ref()).map_err(ExecutorError::SignerError)?;
+        Self::from_json(&content)
+    }
+
+    /// `sign` used to build the signature headers of given request body
+    pub fn sign(&self, body: &[u8]) -> Vec<(&'static str, String)> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let nonce = hex::encode(rand::random::<[u8; 16]>());
+
+        self.sign_with(body, timestamp, &nonce)
+    }
+
+    fn sign_with(&self, body: &[u8], timestamp: u64, nonce: &str) -> Vec<(&'static str, String)> {
+        let message = signing_message(&self.id, timestamp, nonce, body);
+        let signature = match self.key.as_ref() {
+            Key::Hmac(key) => hex::encode(hmac::sign(key, &message)),
+            Key::Ed25519(key) => hex::encode(key.sign(&message).to_bytes()),
+        };
+
+        vec![
+            (SIGNATURE_KEY_ID_HEADER, self.id.clone()),
+            (SIGNATURE_NONCE_HEADER, nonce.to_string()),
+            (SIGNATURE_TIMESTAMP_HEADER, timestamp.to_string()),
+            (SIGNATURE_HEADER, signature),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rst_common::with_cryptography::ed25519_dalek::{Signature, Verifier};
+
+    #[test]
+    fn test_request_signer() {
+        let body = br#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1}"#;
+        let signer = RequestSigner::hmac("agent-1", b"secret");
+
+        let headers = signer.sign_with(body, 1700000000, "n1");
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+        let message = signing_message("agent-1", 1700000000, "n1", body);
+        assert_eq!(
+            headers,
+            vec![
+                (SIGNATURE_KEY_ID_HEADER, String::from("agent-1")),
+                (SIGNATURE_NONCE_HEADER, String::from("n1")),
+                (SIGNATURE_TIMESTAMP_HEADER, String::from("1700000000")),
+                (SIGNATURE_HEADER, hex::encode(hmac::sign(&key, &message))),
+            ]
+        );
+
+        let first = signer.sign(body);
+        let second = signer.sign(body);
+        assert_ne!(first[1], second[1]);
+
+        let content = format!(
+            r#"{{"id":"agent-2","algorithm":"ed25519","key":"{}"}}"#,
+            hex::encode([7u8; 32])
+        );
+        let signer = RequestSigner::from_json(&content).unwrap();
+        let headers = signer.sign_with(body, 1700000000, "n1");
+
+        let signature = Signature::from_slice(&hex::decode(&headers[3].1).unwrap()).unwrap();
+        let message = signing_message("agent-2", 1700000000, "n1", body);
+        let public_key = SigningKey::from_bytes(&[7u8; 32]).verifying_key();
+        assert!(public_key.verify(&message, &signature).is_ok());
+
+        assert!(
+            RequestSigner::from_json(r#"{"id":"a","algorithm":"ed25519","key":"ab"}"#).is_err()
+        );
+        assert!(RequestSigner::from_file("/unknown/key.json").is_err())
+    }
+}
@@ -0,0 +1,109 @@
+use std::path::Path;
+
+use rst_common::standard::reqwest::{Certificate, ClientBuilder, Identity};
+
+use crate::types::{read_file, ExecutorError};
+
+/// `ClientTls` used to configure the TLS connections of the executors
+///
+/// It's needed when the server certificate is signed by a private CA, or when the server
+/// requires the clients to present their own certificates (mutual TLS)
+#[derive(Clone, Default)]
+pub struct ClientTls {
+    root_certificates: Vec<Certificate>,
+    identity: Option<Identity>,
+}
+
+impl ClientTls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `with_root_ca` used to trust the PEM encoded CA certificates, the PEM may contain
+    /// multiple certificates
+    pub fn with_root_ca(mut self, pem: &[u8]) -> Result<Self, ExecutorError> {
+        let certs = Certificate::from_pem_bundle(pem)
+            .map_err(|err| ExecutorError::TlsError(err.to_string()))?;
+
+        if certs.is_empty() {
+            return Err(ExecutorError::TlsError(String::from(
+                "missing root certificates",
+            )));
+        }
+
+        self.root_certificates.extend(certs);
+        Ok(self)
+    }
+
+    pub fn with_root_ca_file<P: AsRef<Path>>(self, path: P) -> Result<Self, ExecutorError> {
+        let pem = read_file(path.as_ref()).map_err(ExecutorError::TlsError)?;
+        self.with_root_ca(pem.as_bytes())
+    }
+
+    /// `with_identity` used to present the client certificate, from the PEM encoded
+    /// certificate chain and its private key
+    pub fn with_identity(mut self, cert_pem: &[u8], key_pem: &[u8]) -> Result<Self, ExecutorError> {
+        let pem = [cert_pem, b"\n", key_pem].concat();
+        let identity =
+            Identity::from_pem(&pem).map_err(|err| ExecutorError::TlsError(err.to_string()))?;
+
+        self.identity = Some(identity);
+        Ok(self)
+    }
+
+    pub fn with_identity_files<P: AsRef<Path>>(
+        self,
+        cert_path: P,
+        key_path: P,
+    ) -> Result<Self, ExecutorError> {
+        let cert_pem = read_file(cert_path.as_ref()).map_err(ExecutorError::TlsError)?;
+        let key_pem = read_file(key_path.as_ref()).map_err(ExecutorError::TlsError)?;
+        self.with_identity(cert_pem.as_bytes(), key_pem.as_bytes())
+    }
+
+    pub(crate) fn apply(&self, mut builder: ClientBuilder) -> ClientBuilder {
+        builder = builder.use_rustls_tls();
+        for cert in self.root_certificates.iter() {
+            builder = builder.add_root_certificate(cert.clone());
+        }
+
+        if let Some(identity) = self.identity.as_ref() {
+            builder = builder.identity(identity.clone());
+        }
+
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rcgen::{CertificateParams, KeyPair};
+
+    #[test]
+    fn test_client_tls() {
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec![String::from("agent-1")])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+
+        let tls = ClientTls::new()
+            .with_root_ca(cert.pem().as_bytes())
+            .unwrap()
+            .with_identity(cert.pem().as_bytes(), key.serialize_pem().as_bytes())
+            .unwrap();
+        assert_eq!(tls.root_certificates.len(), 1);
+        assert!(tls.identity.is_some());
+        assert!(tls.apply(ClientBuilder::new()).build().is_ok());
+
+        assert!(ClientTls::new().with_root_ca(b"invalid").is_err());
+        assert!(ClientTls::new()
+            .with_identity(cert.pem().as_bytes(), b"invalid")
+            .is_err());
+        assert!(ClientTls::new()
+            .with_root_ca_file("/unknown/ca.pem")
+            .is_err())
+    }
+}
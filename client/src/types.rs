@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use prople_jsonrpc_core::types::{RpcErrorBuilder, RpcId};
 
 use rst_common::standard::async_trait::async_trait;
@@ -21,6 +23,21 @@ pub enum ExecutorError {
 
     #[error("executor error: missing result")]
     MissingResult,
+
+    #[error("executor error: token error: {0}")]
+    TokenError(String),
+
+    #[error("executor error: signer error: {0}")]
+    SignerError(String),
+
+    #[error("executor error: tls error: {0}")]
+    TlsError(String),
+}
+
+/// `read_file` used to read the content of the key and certificate files, the error
+/// message will include the file path
+pub(crate) fn read_file(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))
 }
 
 /// `RpcValue` used to convert any value types to the
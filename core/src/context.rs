@@ -0,0 +1,427 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use rst_common::standard::serde_json::{json, Value};
+
+use crate::objects::RpcCancellation;
+use crate::types::{RpcError, RpcId, RpcMethod};
+
+/// `StateBoxed` is an alias type used as shortcut to the type-erased shared state
+pub type StateBoxed = Arc<dyn Any + Send + Sync>;
+
+/// `Extensions` is a typed map used to store any values by its own type
+///
+/// The values are stored behind an [`Arc`], so cloning the map will be cheap
+/// and all clones will share the same values
+#[derive(Clone, Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `insert` used to insert a value, it will replace previous value of the same type
+    pub fn insert<T>(&mut self, value: T)
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    /// `get` used to get a value by its type
+    pub fn get<T>(&self) -> Option<&T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+    }
+
+    /// `contains` used to check if there is a value of given type
+    pub fn contains<T>(&self) -> bool
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// `remove` used to remove a value by its type
+    pub fn remove<T>(&mut self) -> bool
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map.remove(&TypeId::of::<T>()).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+/// `Principal` is the authenticated identity of current caller
+///
+/// It's resolved by the transport layer authenticator, and it can be read by the handlers
+/// through [`RpcContext::principal`] or extracted directly by the function handlers
+#[derive(Clone, Debug, PartialEq)]
+pub struct Principal {
+    id: String,
+    scopes: Vec<String>,
+    attributes: HashMap<String, Value>,
+}
+
+impl Principal {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            scopes: Vec::new(),
+            attributes: HashMap::new(),
+        }
+    }
+
+    /// `with_scopes` used to grant given scopes to the caller, they will be checked
+    /// against the scopes required by the routes
+    pub fn with_scopes<S: ToString>(mut self, scopes: &[S]) -> Self {
+        for scope in scopes {
+            let scope = scope.to_string();
+            if !self.scopes.contains(&scope) {
+                self.scopes.push(scope);
+            }
+        }
+
+        self
+    }
+
+    /// `with_attribute` used to attach any additional information of the caller
+    pub fn with_attribute(mut self, key: &str, value: Value) -> Self {
+        self.attributes.insert(key.to_string(), value);
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn scopes(&self) -> &[String] {
+        &self.scopes
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|granted| granted == scope)
+    }
+
+    pub fn attribute(&self, key: &str) -> Option<&Value> {
+        self.attributes.get(key)
+    }
+}
+
+/// `RpcContext` is a per-request object passed to each handler call
+///
+/// The transport layer (for example, the `Axum` handler) is responsible to build
+/// this object, including for its transport metadata such as headers and the remote
+/// address. The [`crate::objects::RpcProcessor`] will fill the request `id`, `method` and
+/// its shared state before calling the handler. Any other values, like an authenticated
+/// identity, can be stored into its typed [`Extensions`]
+#[derive(Clone, Default)]
+pub struct RpcContext {
+    id: Option<RpcId>,
+    method: Option<RpcMethod>,
+    state: Option<StateBoxed>,
+    remote_addr: Option<SocketAddr>,
+    headers: HashMap<String, Vec<String>>,
+    extensions: Extensions,
+    cancellation: RpcCancellation,
+}
+
+impl RpcContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `id` used to get current request id, a `None` value means current request
+    /// is a notification
+    pub fn id(&self) -> Option<&RpcId> {
+        self.id.as_ref()
+    }
+
+    /// `method` used to get current request method
+    pub fn method(&self) -> Option<&RpcMethod> {
+        self.method.as_ref()
+    }
+
+    /// `state` used to get the shared application state registered to the processor
+    ///
+    /// It will return `None` if there is no state or the state is not of given type
+    pub fn state<S>(&self) -> Option<Arc<S>>
+    where
+        S: Send + Sync + 'static,
+    {
+        self.state
+            .clone()
+            .and_then(|state| state.downcast::<S>().ok())
+    }
+
+    pub(crate) fn set_state(&mut self, state: Option<StateBoxed>) -> &mut Self {
+        self.state = state;
+        self
+    }
+
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    pub fn set_remote_addr(&mut self, addr: SocketAddr) -> &mut Self {
+        self.remote_addr = Some(addr);
+        self
+    }
+
+    /// `insert_header` used to append a header value, the header name will be
+    /// stored in lowercase
+    pub fn insert_header(&mut self, name: &str, value: &str) -> &mut Self {
+        self.headers
+            .entry(name.to_lowercase())
+            .or_default()
+            .push(value.to_string());
+        self
+    }
+
+    /// `header` used to get the first value of given header name, case-insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_lowercase())
+            .and_then(|values| values.first())
+            .map(|value| value.as_str())
+    }
+
+    /// `header_values` used to get all values of given header name, case-insensitive
+    pub fn header_values(&self, name: &str) -> Vec<&str> {
+        self.headers
+            .get(&name.to_lowercase())
+            .map(|values| values.iter().map(|value| value.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// `principal` used to get the authenticated caller, if any
+    pub fn principal(&self) -> Option<&Principal> {
+        self.extensions.get::<Principal>()
+    }
+
+    pub fn set_principal(&mut self, principal: Principal) -> &mut Self {
+        self.extensions.insert(principal);
+        self
+    }
+
+    /// `cancellation` used to get the cancellation token of current request
+    ///
+    /// The token will be cancelled when the request has timed out, or when the
+    /// transport layer cancels it, for example because the client has disconnected
+    pub fn cancellation(&self) -> &RpcCancellation {
+        &self.cancellation
+    }
+
+    /// `set_cancellation` used by the transport layer to link the requests to its own token
+    pub fn set_cancellation(&mut self, cancellation: RpcCancellation) -> &mut Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// `for_request` used to build the context of a single request, each request will
+    /// receive its own child cancellation token
+    pub(crate) fn for_request(&self, id: Option<RpcId>, method: RpcMethod) -> Self {
+        let mut ctx = self.clone();
+        ctx.id = id;
+        ctx.method = Some(method);
+        ctx.cancellation = self.cancellation.child_token();
+        ctx
+    }
+}
+
+/// `FromContext` used to extract a value from the [`RpcContext`]
+///
+/// It's designed to be used by the function handlers, so they can receive any
+/// values extracted from the context before the request params
+pub trait FromContext: Sized {
+    fn from_context(ctx: &RpcContext) -> Result<Self, RpcError>;
+}
+
+impl FromContext for RpcContext {
+    fn from_context(ctx: &RpcContext) -> Result<Self, RpcError> {
+        Ok(ctx.clone())
+    }
+}
+
+impl FromContext for Principal {
+    fn from_context(ctx: &RpcContext) -> Result<Self, RpcError> {
+        ctx.principal().cloned().ok_or(RpcError::Unauthorized)
+    }
+}
+
+/// `SharedState` is an extractor used to get the shared application state
+/// registered to the processor, similar with the `Axum` state extractor
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use prople_jsonrpc_core::objects::{RpcProcessor, RpcSharedState};
+/// use prople_jsonrpc_core::types::RpcError;
+///
+/// struct AppState {
+///     greeting: String,
+/// }
+///
+/// let mut processor = RpcProcessor::new();
+/// processor
+///     .set_state(Arc::new(AppState {
+///         greeting: String::from("hello"),
+///     }))
+///     .method(
+///         "user.greet",
+///         |RpcSharedState(state): RpcSharedState<AppState>, (name,): (String,)| async move {
+///             Ok::<_, RpcError>(format!("{} {}", state.greeting, name))
+///         },
+///     );
+/// ```
+///
+/// If the state is missing, it will return [`RpcError::InternalError`]
+pub struct SharedState<S>(pub Arc<S>);
+
+impl<S> Clone for SharedState<S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<S> FromContext for SharedState<S>
+where
+    S: Send + Sync + 'static,
+{
+    fn from_context(ctx: &RpcContext) -> Result<Self, RpcError> {
+        ctx.state::<S>().map(SharedState).ok_or_else(|| {
+            RpcError::InternalError.with_data(json!({"reason": "missing shared state"}))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct FakeIdentity(String);
+
+    #[test]
+    fn test_extensions() {
+        let mut extensions = Extensions::new();
+        assert!(extensions.is_empty());
+
+        extensions.insert(FakeIdentity(String::from("alice")));
+        extensions.insert(10u64);
+        assert_eq!(extensions.len(), 2);
+        assert!(extensions.contains::<u64>());
+        assert_eq!(
+            extensions.get::<FakeIdentity>(),
+            Some(&FakeIdentity(String::from("alice")))
+        );
+
+        extensions.insert(FakeIdentity(String::from("bob")));
+        assert_eq!(
+            extensions.get::<FakeIdentity>(),
+            Some(&FakeIdentity(String::from("bob")))
+        );
+
+        assert!(extensions.remove::<u64>());
+        assert!(!extensions.remove::<u64>());
+        assert!(extensions.get::<u64>().is_none());
+    }
+
+    #[test]
+    fn test_context_headers() {
+        let mut ctx = RpcContext::new();
+        ctx.insert_header("X-Request-Id", "abc")
+            .insert_header("accept", "application/json")
+            .insert_header("Accept", "text/plain");
+
+        assert_eq!(ctx.header("x-request-id"), Some("abc"));
+        assert_eq!(ctx.header("ACCEPT"), Some("application/json"));
+        assert_eq!(
+            ctx.header_values("accept"),
+            vec!["application/json", "text/plain"]
+        );
+        assert!(ctx.header("unknown").is_none());
+    }
+
+    #[test]
+    fn test_context_for_request() {
+        let mut ctx = RpcContext::new();
+        ctx.set_remote_addr("127.0.0.1:8080".parse().unwrap());
+        ctx.extensions_mut()
+            .insert(FakeIdentity(String::from("alice")));
+
+        let req_ctx = ctx.for_request(Some(RpcId::IntegerVal(1)), RpcMethod::from("testing"));
+        assert_eq!(req_ctx.id(), Some(&RpcId::IntegerVal(1)));
+        assert_eq!(req_ctx.method(), Some(&RpcMethod::from("testing")));
+        assert_eq!(req_ctx.remote_addr(), ctx.remote_addr());
+        assert!(req_ctx.extensions().contains::<FakeIdentity>());
+        assert!(ctx.id().is_none());
+
+        req_ctx.cancellation().cancel();
+        assert!(!ctx.cancellation().is_cancelled());
+
+        let other_ctx = ctx.for_request(None, RpcMethod::from("testing"));
+        ctx.cancellation().cancel();
+        assert!(other_ctx.cancellation().is_cancelled());
+    }
+
+    #[test]
+    fn test_context_state() {
+        let mut ctx = RpcContext::new();
+        assert!(ctx.state::<String>().is_none());
+        assert!(SharedState::<String>::from_context(&ctx).is_err());
+
+        ctx.set_state(Some(Arc::new(String::from("shared"))));
+        assert_eq!(ctx.state::<String>().unwrap().as_str(), "shared");
+        assert!(ctx.state::<u64>().is_none());
+
+        let SharedState(state) = SharedState::<String>::from_context(&ctx).unwrap();
+        assert_eq!(state.as_str(), "shared")
+    }
+
+    #[test]
+    fn test_context_principal() {
+        let mut ctx = RpcContext::new();
+        assert!(ctx.principal().is_none());
+        assert!(Principal::from_context(&ctx).is_err());
+
+        ctx.set_principal(Principal::new("alice").with_attribute("kind", json!("api-key")));
+        assert_eq!(
+            ctx.principal().map(|principal| principal.id()),
+            Some("alice")
+        );
+
+        let principal = Principal::from_context(&ctx).unwrap();
+        assert_eq!(principal.attribute("kind"), Some(&json!("api-key")));
+        assert!(principal.scopes().is_empty());
+
+        let principal = principal.with_scopes(&["read", "write", "read"]);
+        assert_eq!(principal.scopes(), &["read", "write"]);
+        assert!(principal.has_scope("write"));
+        assert!(!principal.has_scope("admin"))
+    }
+}
@@ -1,4 +1,8 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
 use rst_common::standard::serde::{self, Deserialize, Serialize};
+use rst_common::standard::serde_json::Value;
 use rst_common::with_errors::thiserror::{self, Error};
 
 pub type RpcErrorCode = i64;
@@ -9,12 +13,46 @@ pub const INVALID_REQUEST_CODE: RpcErrorCode = -32600;
 pub const METHOD_NOT_FOUND_CODE: RpcErrorCode = -32601;
 pub const INVALID_PARAMS_CODE: RpcErrorCode = -32602;
 pub const INTERNAL_ERROR_CODE: RpcErrorCode = -32603;
+pub const SERVER_ERROR_CODE: RpcErrorCode = -32000;
+pub const REQUEST_TIMEOUT_CODE: RpcErrorCode = -32001;
+pub const SERVER_BUSY_CODE: RpcErrorCode = -32002;
+pub const RATE_LIMITED_CODE: RpcErrorCode = -32003;
+pub const UNAUTHORIZED_CODE: RpcErrorCode = -32004;
+pub const FORBIDDEN_CODE: RpcErrorCode = -32005;
 
 pub const PARSE_ERROR_MESSAGE: RpcErrorMessage = "Parse error";
 pub const INVALID_REQUEST_MESSAGE: RpcErrorMessage = "Invalid request";
 pub const METHOD_NOT_FOUND_MESSAGE: RpcErrorMessage = "Method not found";
 pub const INVALID_PARAMS_MESSAGE: RpcErrorMessage = "Invalid params";
 pub const INTERNAL_ERROR_MESSAGE: RpcErrorMessage = "Internal error";
+pub const SERVER_ERROR_MESSAGE: RpcErrorMessage = "Server error";
+pub const REQUEST_TIMEOUT_MESSAGE: RpcErrorMessage = "Request timeout";
+pub const SERVER_BUSY_MESSAGE: RpcErrorMessage = "Server busy";
+pub const RATE_LIMITED_MESSAGE: RpcErrorMessage = "Rate limit exceeded";
+pub const UNAUTHORIZED_MESSAGE: RpcErrorMessage = "Unauthorized";
+pub const FORBIDDEN_MESSAGE: RpcErrorMessage = "Forbidden";
+pub const APPLICATION_ERROR_MESSAGE: RpcErrorMessage = "Application error";
+
+/// `RETRY_AFTER_KEY` is the error data key of the retry hint, in seconds, given to
+/// the rejected busy calls
+pub const RETRY_AFTER_KEY: &str = "retry_after";
+
+/// `RESET_AFTER_KEY` is the error data key of the time, in seconds, until the rate limited
+/// calls are allowed again
+pub const RESET_AFTER_KEY: &str = "reset_after";
+
+/// `RESERVED_ERROR_CODES` is the range of error codes reserved by the specification
+pub const RESERVED_ERROR_CODES: RangeInclusive<RpcErrorCode> = -32768..=-32000;
+
+/// `SERVER_ERROR_CODES` is the range of error codes reserved for implementation-defined
+/// server errors. It's the only reserved range allowed to be used by [`RpcError::Custom`]
+pub const SERVER_ERROR_CODES: RangeInclusive<RpcErrorCode> = -32099..=-32000;
+
+/// `is_custom_code` used to check if given code is allowed to be used by [`RpcError::Custom`],
+/// which means it must be in the server error range or outside of the reserved range
+pub fn is_custom_code(code: RpcErrorCode) -> bool {
+    !RESERVED_ERROR_CODES.contains(&code) || SERVER_ERROR_CODES.contains(&code)
+}
 
 /// `RpcError` is the only error data structures, that should be
 /// cover all required error types based on the `JSON-RPC` specification
@@ -35,19 +73,234 @@ pub enum RpcError {
     #[error("internal error")]
     InternalError,
 
+    #[error("request timeout")]
+    RequestTimeout,
+
+    #[error("server busy")]
+    ServerBusy,
+
+    #[error("rate limit exceeded")]
+    RateLimited,
+
+    #[error("unauthorized")]
+    Unauthorized,
+
+    #[error("forbidden")]
+    Forbidden,
+
     #[error("handler error: {0}")]
-    HandlerError(String)
+    HandlerError(String),
+
+    #[error("{error}")]
+    WithData { error: Box<RpcError>, data: Value },
+
+    #[error("custom error: {code}")]
+    Custom {
+        code: RpcErrorCode,
+        message: Option<String>,
+        data: Option<Value>,
+    },
 }
 
 impl RpcError {
+    /// `with_data` used to attach additional information to the error object
+    ///
+    /// The given data will be serialized as the `data` member of the error object,
+    /// it may contains anything such as validation details, field names or retry hints
+    pub fn with_data(self, data: Value) -> Self {
+        match self {
+            RpcError::WithData { error, .. } => RpcError::WithData { error, data },
+            RpcError::Custom { code, message, .. } => RpcError::Custom {
+                code,
+                message,
+                data: Some(data),
+            },
+            error => RpcError::WithData {
+                error: Box::new(error),
+                data,
+            },
+        }
+    }
+
+    /// `data` used to get attached error data, if any
+    pub fn data(&self) -> Option<Value> {
+        match self {
+            RpcError::WithData { data, .. } => Some(data.clone()),
+            RpcError::Custom { data, .. } => data.clone(),
+            _ => None,
+        }
+    }
+
+    /// `custom` used to build [`RpcError::Custom`] without any message and data
+    ///
+    /// The error message will be taken from the [`RpcErrorCatalog`] of the processor
+    /// when the error is built. A code which is not allowed by [`is_custom_code`] will
+    /// be rejected and answered with [`RpcError::InternalError`]
+    pub fn custom(code: RpcErrorCode) -> Self {
+        RpcError::Custom {
+            code,
+            message: None,
+            data: None,
+        }
+    }
+
     pub fn build(&self) -> (RpcErrorCode, String) {
         match self {
             RpcError::ParseError => (PARSE_ERROR_CODE, PARSE_ERROR_MESSAGE.to_string()),
-            RpcError::MethodNotFound => (METHOD_NOT_FOUND_CODE, METHOD_NOT_FOUND_MESSAGE.to_string()),
+            RpcError::MethodNotFound => {
+                (METHOD_NOT_FOUND_CODE, METHOD_NOT_FOUND_MESSAGE.to_string())
+            }
             RpcError::InvalidRequest => (INVALID_REQUEST_CODE, INVALID_REQUEST_MESSAGE.to_string()),
             RpcError::InvalidParams => (INVALID_PARAMS_CODE, INVALID_PARAMS_MESSAGE.to_string()),
             RpcError::InternalError => (INTERNAL_ERROR_CODE, INTERNAL_ERROR_MESSAGE.to_string()),
-            RpcError::HandlerError(herr) => (INTERNAL_ERROR_CODE, herr.clone()) 
+            RpcError::RequestTimeout => (REQUEST_TIMEOUT_CODE, REQUEST_TIMEOUT_MESSAGE.to_string()),
+            RpcError::ServerBusy => (SERVER_BUSY_CODE, SERVER_BUSY_MESSAGE.to_string()),
+            RpcError::RateLimited => (RATE_LIMITED_CODE, RATE_LIMITED_MESSAGE.to_string()),
+            RpcError::Unauthorized => (UNAUTHORIZED_CODE, UNAUTHORIZED_MESSAGE.to_string()),
+            RpcError::Forbidden => (FORBIDDEN_CODE, FORBIDDEN_MESSAGE.to_string()),
+            RpcError::HandlerError(herr) => (INTERNAL_ERROR_CODE, herr.clone()),
+            RpcError::WithData { error, .. } => error.build(),
+            RpcError::Custom { code, .. } if !is_custom_code(*code) => {
+                (INTERNAL_ERROR_CODE, INTERNAL_ERROR_MESSAGE.to_string())
+            }
+            RpcError::Custom { code, message, .. } => {
+                let message = message.clone().unwrap_or_else(|| {
+                    if SERVER_ERROR_CODES.contains(code) {
+                        SERVER_ERROR_MESSAGE.to_string()
+                    } else {
+                        APPLICATION_ERROR_MESSAGE.to_string()
+                    }
+                });
+
+                (*code, message)
+            }
+        }
+    }
+}
+
+/// `RpcCatalogError` is an error type used when registering an error code into
+/// the [`RpcErrorCatalog`]
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum RpcCatalogError {
+    #[error("catalog error: reserved code: {0}")]
+    ReservedCode(RpcErrorCode),
+
+    #[error("catalog error: duplicate code: {0}")]
+    DuplicateCode(RpcErrorCode),
+}
+
+/// `RpcRouterError` is an error type used when composing multiple
+/// [`crate::objects::RpcProcessor`] together
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum RpcRouterError {
+    #[error("router error: duplicate method: {0}")]
+    DuplicateMethod(String),
+
+    #[error("router error: invalid prefix: {0}")]
+    InvalidPrefix(String),
+}
+
+/// `RpcErrorEntry` is a single record of [`RpcErrorCatalog`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "self::serde")]
+pub struct RpcErrorEntry {
+    pub code: RpcErrorCode,
+    pub message: String,
+}
+
+/// `RpcErrorCatalog` is a registry of known error codes and their messages
+///
+/// It's pre-populated with all of error codes defined by the specification. Applications
+/// may register their own codes, either from the server error range (-32099..-32000) or
+/// any codes outside of the reserved range (-32768..-32000). Each processor owns its own
+/// catalog, the registered messages will be used by its [`RpcError::Custom`] which has no
+/// message, and all entries can be listed to generate an API documentation
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcErrorCatalog {
+    entries: BTreeMap<RpcErrorCode, String>,
+}
+
+impl Default for RpcErrorCatalog {
+    fn default() -> Self {
+        let entries = [
+            (PARSE_ERROR_CODE, PARSE_ERROR_MESSAGE),
+            (INVALID_REQUEST_CODE, INVALID_REQUEST_MESSAGE),
+            (METHOD_NOT_FOUND_CODE, METHOD_NOT_FOUND_MESSAGE),
+            (INVALID_PARAMS_CODE, INVALID_PARAMS_MESSAGE),
+            (INTERNAL_ERROR_CODE, INTERNAL_ERROR_MESSAGE),
+            (SERVER_ERROR_CODE, SERVER_ERROR_MESSAGE),
+            (REQUEST_TIMEOUT_CODE, REQUEST_TIMEOUT_MESSAGE),
+            (SERVER_BUSY_CODE, SERVER_BUSY_MESSAGE),
+            (RATE_LIMITED_CODE, RATE_LIMITED_MESSAGE),
+            (UNAUTHORIZED_CODE, UNAUTHORIZED_MESSAGE),
+            (FORBIDDEN_CODE, FORBIDDEN_MESSAGE),
+        ];
+
+        let entries = entries
+            .into_iter()
+            .map(|(code, message)| (code, message.to_string()))
+            .collect();
+
+        Self { entries }
+    }
+}
+
+impl RpcErrorCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `register` used to register a new error code with its default message
+    ///
+    /// It will return an error if the code has already been registered or if the code
+    /// is part of the reserved range but not in the server error range
+    pub fn register(
+        &mut self,
+        code: RpcErrorCode,
+        message: &str,
+    ) -> Result<&mut Self, RpcCatalogError> {
+        if !is_custom_code(code) {
+            return Err(RpcCatalogError::ReservedCode(code));
+        }
+
+        if self.entries.contains_key(&code) {
+            return Err(RpcCatalogError::DuplicateCode(code));
+        }
+
+        self.entries.insert(code, message.to_string());
+        Ok(self)
+    }
+
+    /// `message` used to get registered message of given error code
+    pub fn message(&self, code: RpcErrorCode) -> Option<String> {
+        self.entries.get(&code).cloned()
+    }
+
+    /// `entries` used to list all registered error codes, ordered by its code
+    pub fn entries(&self) -> Vec<RpcErrorEntry> {
+        self.entries
+            .iter()
+            .map(|(code, message)| RpcErrorEntry {
+                code: *code,
+                message: message.clone(),
+            })
+            .collect()
+    }
+
+    /// `build` used to build the error object of given error, the message of
+    /// [`RpcError::Custom`] which has no message will be taken from this catalog
+    pub fn build(&self, err: RpcError) -> RpcErrorBuilder {
+        let err_obj = RpcErrorBuilder::build(err.clone());
+        match err {
+            RpcError::Custom {
+                code,
+                message: None,
+                ..
+            } if is_custom_code(code) => match self.message(code) {
+                Some(message) => RpcErrorBuilder { message, ..err_obj },
+                None => err_obj,
+            },
+            _ => err_obj,
         }
     }
 }
@@ -61,23 +314,49 @@ impl RpcError {
 pub struct RpcErrorBuilder {
     pub code: RpcErrorCode,
     pub message: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
 }
 
 impl RpcErrorBuilder {
+    /// `build` used to build the error object of given error, a rejected [`RpcError::Custom`]
+    /// will be built as [`RpcError::InternalError`] without any of its data
     pub fn build(err: RpcError) -> Self {
         let (code, message) = err.build();
+        let data = match err {
+            RpcError::Custom { code, .. } if !is_custom_code(code) => None,
+            err => err.data(),
+        };
+
         RpcErrorBuilder {
             code,
             message: message.to_string(),
+            data,
         }
     }
+
+    /// `with_data` used to set or replace the error object `data` member
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// `retry_after` used to get the retry hint, in seconds, from the error `data`, taken
+    /// from its [`RETRY_AFTER_KEY`] or [`RESET_AFTER_KEY`] member
+    pub fn retry_after(&self) -> Option<u64> {
+        let data = self.data.as_ref()?;
+        data.get(RETRY_AFTER_KEY)
+            .or(data.get(RESET_AFTER_KEY))
+            .and_then(|retry_after| retry_after.as_u64())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use rst_common::standard::serde_json;
+    use rst_common::standard::serde_json::{self, json};
     use rst_common::with_tests::table_test::table_test;
 
     #[test]
@@ -103,6 +382,38 @@ mod tests {
                 RpcError::InternalError,
                 (INTERNAL_ERROR_CODE, INTERNAL_ERROR_MESSAGE.to_string()),
             ),
+            (
+                RpcError::InvalidParams.with_data(json!({"field": "name"})),
+                (INVALID_PARAMS_CODE, INVALID_PARAMS_MESSAGE.to_string()),
+            ),
+            (
+                RpcError::custom(-32050),
+                (-32050, SERVER_ERROR_MESSAGE.to_string()),
+            ),
+            (
+                RpcError::custom(1001),
+                (1001, APPLICATION_ERROR_MESSAGE.to_string()),
+            ),
+            (
+                RpcError::custom(PARSE_ERROR_CODE),
+                (INTERNAL_ERROR_CODE, INTERNAL_ERROR_MESSAGE.to_string()),
+            ),
+            (
+                RpcError::Custom {
+                    code: -32100,
+                    message: Some(String::from("Vessel unavailable")),
+                    data: None,
+                },
+                (INTERNAL_ERROR_CODE, INTERNAL_ERROR_MESSAGE.to_string()),
+            ),
+            (
+                RpcError::Custom {
+                    code: 1001,
+                    message: Some(String::from("Insufficient balance")),
+                    data: None,
+                },
+                (1001, String::from("Insufficient balance")),
+            ),
         ];
 
         for (validator, input, expected) in table_test!(table) {
@@ -138,18 +449,147 @@ mod tests {
                 RpcError::InternalError,
                 String::from(r#"{"code":-32603,"message":"Internal error"}"#),
             ),
+            (
+                RpcError::InvalidParams.with_data(json!({"field": "name"})),
+                String::from(
+                    r#"{"code":-32602,"message":"Invalid params","data":{"field":"name"}}"#,
+                ),
+            ),
+            (
+                RpcError::InternalError
+                    .with_data(json!("first"))
+                    .with_data(json!({"retry_after": 10})),
+                String::from(
+                    r#"{"code":-32603,"message":"Internal error","data":{"retry_after":10}}"#,
+                ),
+            ),
+            (
+                RpcError::Custom {
+                    code: 1002,
+                    message: Some(String::from("Account locked")),
+                    data: Some(json!({"until": "tomorrow"})),
+                },
+                String::from(
+                    r#"{"code":1002,"message":"Account locked","data":{"until":"tomorrow"}}"#,
+                ),
+            ),
+            (
+                RpcError::custom(1003).with_data(json!([1, 2])),
+                String::from(r#"{"code":1003,"message":"Application error","data":[1,2]}"#),
+            ),
+            (
+                RpcError::custom(PARSE_ERROR_CODE).with_data(json!({"secret": "payload"})),
+                String::from(r#"{"code":-32603,"message":"Internal error"}"#),
+            ),
         ];
 
         for (validator, input, expected) in table_test!(table) {
             let err: RpcErrorBuilder = RpcErrorBuilder::build(input.clone());
             let errobj = serde_json::to_string(&err);
-            assert!(!errobj.is_err());
+            assert!(errobj.is_ok());
 
             validator
                 .given(&format!("{:?}", input))
                 .when("build error")
                 .then(&format!("it should be: {:?}", expected))
-                .assert_eq(expected, format!("{}", errobj.unwrap()));
+                .assert_eq(expected, errobj.unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn test_deserialize_error_object_with_data() {
+        let jsonstr = r#"{"code":-32602,"message":"Invalid params","data":{"field":"name"}}"#;
+        let err: Result<RpcErrorBuilder, serde_json::Error> = serde_json::from_str(jsonstr);
+        assert!(err.is_ok());
+
+        let errobj = err.unwrap();
+        assert_eq!(errobj.code, INVALID_PARAMS_CODE);
+        assert_eq!(errobj.data, Some(json!({"field": "name"})));
+
+        let jsonstr = r#"{"code":-32602,"message":"Invalid params"}"#;
+        let errobj: RpcErrorBuilder = serde_json::from_str(jsonstr).unwrap();
+        assert!(errobj.data.is_none())
+    }
+
+    #[test]
+    fn test_error_object_retry_after() {
+        let table = vec![
+            (
+                RpcError::ServerBusy.with_data(json!({"retry_after": 3})),
+                Some(3),
+            ),
+            (
+                RpcError::RateLimited.with_data(json!({"reset_after": 60})),
+                Some(60),
+            ),
+            (
+                RpcError::RateLimited.with_data(json!({"reset_after": "soon"})),
+                None,
+            ),
+            (RpcError::ServerBusy, None),
+        ];
+
+        for (input, expected) in table {
+            assert_eq!(RpcErrorBuilder::build(input).retry_after(), expected)
+        }
+    }
+
+    #[test]
+    fn test_catalog_register() {
+        let mut catalog = RpcErrorCatalog::new();
+        let result = catalog.register(-32098, "Vessel unavailable");
+        assert!(result.is_ok());
+
+        let result = catalog.register(2001, "Insufficient balance");
+        assert!(result.is_ok());
+
+        let err_obj = catalog.build(RpcError::custom(-32098));
+        assert_eq!(err_obj.code, -32098);
+        assert_eq!(err_obj.message, "Vessel unavailable");
+
+        let err_obj = catalog.build(RpcError::custom(2001).with_data(json!({"balance": 10})));
+        assert_eq!(err_obj.code, 2001);
+        assert_eq!(err_obj.message, "Insufficient balance");
+        assert_eq!(err_obj.data, Some(json!({"balance": 10})));
+
+        let (_, message) = RpcError::custom(2001).build();
+        assert_eq!(message, APPLICATION_ERROR_MESSAGE);
+        assert!(RpcErrorCatalog::new().message(2001).is_none());
+
+        let entries = catalog.entries();
+        assert!(entries.contains(&RpcErrorEntry {
+            code: 2001,
+            message: String::from("Insufficient balance"),
+        }));
+        assert!(entries.contains(&RpcErrorEntry {
+            code: METHOD_NOT_FOUND_CODE,
+            message: METHOD_NOT_FOUND_MESSAGE.to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_catalog_register_error() {
+        let table = vec![
+            (
+                PARSE_ERROR_CODE,
+                RpcCatalogError::ReservedCode(PARSE_ERROR_CODE),
+            ),
+            (-32100, RpcCatalogError::ReservedCode(-32100)),
+            (-32768, RpcCatalogError::ReservedCode(-32768)),
+            (
+                SERVER_ERROR_CODE,
+                RpcCatalogError::DuplicateCode(SERVER_ERROR_CODE),
+            ),
+        ];
+
+        for (validator, input, expected) in table_test!(table) {
+            let mut catalog = RpcErrorCatalog::new();
+            let result = catalog.register(input, "testing").map(|_| ());
+            validator
+                .given(&format!("{:?}", input))
+                .when("register error code")
+                .then(&format!("it should be: {:?}", expected))
+                .assert_eq(Err(expected), result);
         }
     }
 }
@@ -3,6 +3,7 @@ use rst_common::standard::erased_serde::Serialize as ErasedSerialized;
 use rst_common::standard::serde::{self, Deserialize, Serialize};
 use rst_common::standard::serde_json::Value;
 
+use crate::objects::RpcContext;
 use crate::types::{RpcError, RpcHandler, RpcMethod};
 
 pub const PING_RPC_METHOD: &str = "prople.vessel.ping";
@@ -21,6 +22,7 @@ pub struct AgentPingHandler;
 impl RpcHandler for AgentPingHandler {
     async fn call(
         &self,
+        _: RpcContext,
         _: RpcMethod,
         _: Option<Value>,
     ) -> Result<Option<Box<dyn ErasedSerialized>>, RpcError> {
@@ -42,7 +44,11 @@ mod tests {
     async fn test_agent_ping_call() {
         let handler = AgentPingHandler;
         let response = handler
-            .call(RpcMethod::from(PING_RPC_METHOD), Some(Value::Null))
+            .call(
+                RpcContext::default(),
+                RpcMethod::from(PING_RPC_METHOD),
+                Some(Value::Null),
+            )
             .await;
 
         match response {
@@ -51,7 +57,7 @@ mod tests {
                     let jsonstr = serde_json::to_string(out.as_ref());
                     assert_eq!(r#"{"message":"pong!"}"#, jsonstr.unwrap())
                 }
-                None => assert!(false),
+                None => panic!("missing response"),
             },
             Err(err) => assert_eq!(err.to_string(), String::from("")),
         }
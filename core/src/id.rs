@@ -12,11 +12,17 @@ use rst_common::standard::serde::{self, Deserialize, Serialize};
 /// > The value SHOULD normally not be Null and Numbers SHOULD NOT contain fractional parts
 ///
 /// This object will implement [`serde::de::Visitor`] used to parse given json string and need to
-/// parse the `id` value based on it's type, an integer or a string
+/// parse the `id` value based on it's type, an integer, a string or an explicit `null`.
+///
+/// A non-negative integer will always be parsed as [`RpcId::IntegerVal`], while [`RpcId::SignedVal`]
+/// only used for the negative one, so the `id` will be echoed back exactly as received.
+/// A number with fractional parts will be rejected
 #[derive(Debug, PartialEq, Clone)]
 pub enum RpcId {
     StringVal(String),
     IntegerVal(u64),
+    SignedVal(i64),
+    Null,
 }
 
 struct RpcIdVisitor;
@@ -25,7 +31,7 @@ impl<'de> Visitor<'de> for RpcIdVisitor {
     type Value = RpcId;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        formatter.write_str("expected a value of string or integer")
+        formatter.write_str("expected a value of string, integer or null")
     }
 
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
@@ -41,6 +47,31 @@ impl<'de> Visitor<'de> for RpcIdVisitor {
     {
         Ok(RpcId::IntegerVal(v))
     }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if v >= 0 {
+            return Ok(RpcId::IntegerVal(v as u64));
+        }
+
+        Ok(RpcId::SignedVal(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Err(de::Error::invalid_type(de::Unexpected::Float(v), &self))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RpcId::Null)
+    }
 }
 
 impl Serialize for RpcId {
@@ -50,7 +81,9 @@ impl Serialize for RpcId {
     {
         match self {
             RpcId::IntegerVal(val) => serializer.serialize_u64(*val),
+            RpcId::SignedVal(val) => serializer.serialize_i64(*val),
             RpcId::StringVal(val) => serializer.serialize_str(val.as_str()),
+            RpcId::Null => serializer.serialize_unit(),
         }
     }
 }
@@ -63,3 +96,62 @@ impl<'de> Deserialize<'de> for RpcId {
         deserializer.deserialize_any(RpcIdVisitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rst_common::standard::serde_json;
+    use rst_common::with_tests::table_test::table_test;
+
+    #[test]
+    fn test_deserialize_id() {
+        let table = vec![
+            (r#""abc""#, RpcId::StringVal(String::from("abc"))),
+            (r#""1""#, RpcId::StringVal(String::from("1"))),
+            ("1", RpcId::IntegerVal(1)),
+            ("0", RpcId::IntegerVal(0)),
+            ("18446744073709551615", RpcId::IntegerVal(u64::MAX)),
+            ("-1", RpcId::SignedVal(-1)),
+            ("null", RpcId::Null),
+        ];
+
+        for (validator, input, expected) in table_test!(table) {
+            let result: RpcId = serde_json::from_str(input).unwrap();
+            validator
+                .given(input)
+                .when("deserialize id")
+                .then(&format!("it should be: {:?}", expected))
+                .assert_eq(expected, result);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_id_rejected() {
+        let table = vec!["1.5", "1.0", "true", "[1]", r#"{"id":1}"#];
+
+        for input in table {
+            let result: Result<RpcId, serde_json::Error> = serde_json::from_str(input);
+            assert!(result.is_err(), "input should be rejected: {}", input)
+        }
+    }
+
+    #[test]
+    fn test_serialize_id() {
+        let table = vec![
+            (RpcId::StringVal(String::from("1")), r#""1""#),
+            (RpcId::IntegerVal(1), "1"),
+            (RpcId::SignedVal(-10), "-10"),
+            (RpcId::Null, "null"),
+        ];
+
+        for (validator, input, expected) in table_test!(table) {
+            let result = serde_json::to_string(&input).unwrap();
+            validator
+                .given(&format!("{:?}", input))
+                .when("serialize id")
+                .then(&format!("it should be: {}", expected))
+                .assert_eq(expected.to_string(), result);
+        }
+    }
+}
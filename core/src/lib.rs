@@ -1,19 +1,28 @@
-#![doc = include_str!("../README.md")]
+#![cfg_attr(not(doctest), doc = include_str!("../README.md"))]
 
+mod context;
 mod errors;
 mod handler;
 mod id;
 mod processor;
 mod request;
 mod response;
+mod signing;
 
 pub mod objects {
     use super::*;
 
-    pub use errors::RpcErrorBuilder;
-    pub use processor::RpcProcessor;
-    pub use request::RpcRequest;
-    pub use response::RpcResponse;
+    pub use context::{
+        Extensions as RpcExtensions, FromContext as RpcFromContext, Principal as RpcPrincipal,
+        RpcContext, SharedState as RpcSharedState, StateBoxed as RpcStateBoxed,
+    };
+    pub use errors::{RpcErrorBuilder, RpcErrorCatalog};
+    pub use processor::{RpcProcessor, RpcRateLimiter, RpcRegistry};
+    pub use request::{RpcPayload, RpcRequest, RpcRequestError};
+    pub use response::{RpcPayloadResponse, RpcResponse};
+    pub use tokio_util::sync::{
+        CancellationToken as RpcCancellation, DropGuard as RpcCancellationGuard,
+    };
 }
 
 pub mod handlers {
@@ -28,8 +37,16 @@ pub mod types {
     pub use errors::*;
     pub use id::RpcId;
     pub use processor::types::{
-        RpcController, RpcHandler, RpcHandlerBoxed, RpcHandlerOutput, RpcMethod,
-        RpcResponseSerialized, RpcRoute,
+        parse_params, RpcClock, RpcConcurrencyLimit, RpcController, RpcFnHandler, RpcHandler,
+        RpcHandlerBoxed, RpcHandlerFn, RpcHandlerOutput, RpcKeyExtractor, RpcMethod, RpcMiddleware,
+        RpcMiddlewareBoxed, RpcQuota, RpcRateLimitKey, RpcResponseSerialized, RpcRoute,
+        RpcSystemClock, RpcTypedAdapter, RpcTypedHandler, DEFAULT_RETRY_AFTER,
+    };
+    pub use processor::{DEFAULT_BATCH_CONCURRENCY, FALLBACK_ROUTE};
+    pub use request::JSONRPC_VERSION;
+    pub use signing::{
+        signing_message, SigningAlgorithm as RpcSigningAlgorithm, SIGNATURE_HEADER,
+        SIGNATURE_KEY_ID_HEADER, SIGNATURE_NONCE_HEADER, SIGNATURE_TIMESTAMP_HEADER,
     };
 }
 
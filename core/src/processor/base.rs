@@ -1,9 +1,13 @@
+use std::sync::Arc;
+use std::time::Duration;
+
 use rst_common::standard::async_trait::async_trait;
 use rst_common::standard::dyn_clone::{self, DynClone};
 use rst_common::standard::erased_serde::Serialize as ErasedSerialized;
 use rst_common::standard::serde_json::Value;
 
-use crate::types::RpcError;
+use crate::objects::RpcContext;
+use crate::types::{RpcConcurrencyLimit, RpcError, RpcMiddleware, RpcMiddlewareBoxed};
 
 /// `HandlerBoxed` is an alias type used as shortcut to the boxed handler type
 pub type HandlerBoxed = Box<dyn Handler + Send + Sync>;
@@ -29,12 +33,15 @@ pub type HandlerOutput = Result<Option<ResponseSerialized>, RpcError>;
 pub trait Handler: DynClone {
     /// `call` means we will start *call* the handler to execute it's logic
     ///
+    /// The given [`RpcContext`] contains current request `id`, `method` and any
+    /// metadata provided by the transport layer
+    ///
     /// > **WARNING**
     /// >
     /// > The given resut MUST BE any data types that already implement `serde::Serialize`.
     /// > The problem is, `serde` doesn't provide (or even already remove the feature) to this
     /// > kind of traits, so that's the reason why we're using `erased_serde::Serialize`
-    async fn call(&self, method: Method, params: Option<Value>) -> HandlerOutput;
+    async fn call(&self, ctx: RpcContext, method: Method, params: Option<Value>) -> HandlerOutput;
 }
 
 #[derive(Clone, PartialEq, Hash, Eq, Debug)]
@@ -52,6 +59,7 @@ impl From<String> for Method {
     }
 }
 
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for Method {
     fn to_string(&self) -> String {
         self.0.clone()
@@ -75,10 +83,17 @@ impl Controller {
 }
 
 /// `Route` used to register an [`Method`] with its [`Controller`]
+///
+/// A route may also have its own middlewares, timeout, concurrency limit and required
+/// scopes, which will only be applied to its method
 #[derive(Clone)]
 pub struct Route {
     method: Method,
     controller: Controller,
+    middlewares: Vec<RpcMiddlewareBoxed>,
+    timeout: Option<Duration>,
+    concurrency_limit: Option<RpcConcurrencyLimit>,
+    scopes: Vec<String>,
 }
 
 impl Route {
@@ -86,9 +101,57 @@ impl Route {
         Self {
             method,
             controller: Controller(handler),
+            middlewares: Vec::new(),
+            timeout: None,
+            concurrency_limit: None,
+            scopes: Vec::new(),
         }
     }
 
+    /// `with_middleware` used to apply given middleware only to this route
+    pub fn with_middleware<M>(mut self, middleware: M) -> Self
+    where
+        M: RpcMiddleware + Send + Sync + 'static,
+    {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// `with_timeout` used to override the processor default timeout for this route
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// `with_concurrency_limit` used to limit the in-flight calls of this route
+    pub fn with_concurrency_limit(mut self, limit: RpcConcurrencyLimit) -> Self {
+        self.concurrency_limit = Some(limit);
+        self
+    }
+
+    /// `with_scopes` used to require the caller to have all of given scopes
+    pub fn with_scopes<S: ToString>(mut self, scopes: &[S]) -> Self {
+        self.scopes
+            .extend(scopes.iter().map(|scope| scope.to_string()));
+        self
+    }
+
+    pub fn middlewares(&self) -> Vec<RpcMiddlewareBoxed> {
+        self.middlewares.clone()
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn concurrency_limit(&self) -> Option<RpcConcurrencyLimit> {
+        self.concurrency_limit.clone()
+    }
+
+    pub fn scopes(&self) -> Vec<String> {
+        self.scopes.clone()
+    }
+
     pub fn method(&self) -> Method {
         self.method.clone()
     }
@@ -0,0 +1,235 @@
+use std::future::Future;
+use std::marker::PhantomData;
+
+use rst_common::standard::async_trait::async_trait;
+use rst_common::standard::futures::future::BoxFuture;
+use rst_common::standard::futures::FutureExt;
+use rst_common::standard::serde::de::DeserializeOwned;
+use rst_common::standard::serde::Serialize;
+use rst_common::standard::serde_json::Value;
+
+use crate::objects::{RpcContext, RpcFromContext};
+use crate::types::RpcError;
+
+use super::base::{Handler, HandlerOutput, Method, ResponseSerialized};
+use super::typed::parse_params;
+
+/// `HandlerFn` is a trait implemented by plain async functions and closures which
+/// can be used as a request handler
+///
+/// The `Args` type parameter only used to differentiate the function signatures,
+/// supported signatures are:
+///
+/// - `Fn() -> Future<Output = Result<R, RpcError>>`
+/// - `Fn(P) -> Future<Output = Result<R, RpcError>>`
+/// - `Fn(E1, P) -> Future<Output = Result<R, RpcError>>`
+/// - `Fn(E1, E2, P) -> Future<Output = Result<R, RpcError>>`
+///
+/// The `P` is the params type which will be parsed using [`parse_params`], while
+/// the `E1` and `E2` are any types implementing [`RpcFromContext`], such as the [`RpcContext`] itself
+pub trait HandlerFn<Args>: Clone + Send + Sync + 'static {
+    fn invoke(
+        &self,
+        ctx: RpcContext,
+        method: Method,
+        params: Option<Value>,
+    ) -> BoxFuture<'static, HandlerOutput>;
+}
+
+impl<F, Fut, R> HandlerFn<()> for F
+where
+    F: Fn() -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<R, RpcError>> + Send + 'static,
+    R: Serialize + Send + Sync + 'static,
+{
+    fn invoke(
+        &self,
+        _: RpcContext,
+        _: Method,
+        _: Option<Value>,
+    ) -> BoxFuture<'static, HandlerOutput> {
+        let fut = self();
+        async move {
+            let output = fut.await?;
+            Ok(Some(Box::new(output) as ResponseSerialized))
+        }
+        .boxed()
+    }
+}
+
+impl<F, Fut, P, R> HandlerFn<(P,)> for F
+where
+    F: Fn(P) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<R, RpcError>> + Send + 'static,
+    P: DeserializeOwned + Send + 'static,
+    R: Serialize + Send + Sync + 'static,
+{
+    fn invoke(
+        &self,
+        _: RpcContext,
+        _: Method,
+        params: Option<Value>,
+    ) -> BoxFuture<'static, HandlerOutput> {
+        let parsed = parse_params::<P>(params);
+        let handler = self.clone();
+
+        async move {
+            let output = handler(parsed?).await?;
+            Ok(Some(Box::new(output) as ResponseSerialized))
+        }
+        .boxed()
+    }
+}
+
+macro_rules! impl_handler_fn {
+    ($($extractor:ident),+) => {
+        impl<F, Fut, $($extractor,)+ P, R> HandlerFn<($($extractor,)+ P)> for F
+        where
+            F: Fn($($extractor,)+ P) -> Fut + Clone + Send + Sync + 'static,
+            Fut: Future<Output = Result<R, RpcError>> + Send + 'static,
+            $($extractor: RpcFromContext + Send + 'static,)+
+            P: DeserializeOwned + Send + 'static,
+            R: Serialize + Send + Sync + 'static,
+        {
+            #[allow(non_snake_case)]
+            fn invoke(
+                &self,
+                ctx: RpcContext,
+                _: Method,
+                params: Option<Value>,
+            ) -> BoxFuture<'static, HandlerOutput> {
+                $(let $extractor = $extractor::from_context(&ctx);)+
+                let parsed = parse_params::<P>(params);
+                let handler = self.clone();
+
+                async move {
+                    let output = handler($($extractor?,)+ parsed?).await?;
+                    Ok(Some(Box::new(output) as ResponseSerialized))
+                }
+                .boxed()
+            }
+        }
+    };
+}
+
+impl_handler_fn!(E1);
+impl_handler_fn!(E1, E2);
+
+/// `FnHandler` is an object used to adapt the [`HandlerFn`] into the [`Handler`]
+pub struct FnHandler<F, Args> {
+    handler: F,
+    _phantom0: PhantomData<fn() -> Args>,
+}
+
+impl<F, Args> FnHandler<F, Args>
+where
+    F: HandlerFn<Args>,
+{
+    pub fn new(handler: F) -> Self {
+        Self {
+            handler,
+            _phantom0: PhantomData,
+        }
+    }
+}
+
+impl<F, Args> Clone for FnHandler<F, Args>
+where
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            handler: self.handler.clone(),
+            _phantom0: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<F, Args> Handler for FnHandler<F, Args>
+where
+    F: HandlerFn<Args>,
+    Args: 'static,
+{
+    async fn call(&self, ctx: RpcContext, method: Method, params: Option<Value>) -> HandlerOutput {
+        self.handler.invoke(ctx, method, params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rst_common::standard::serde_json::{self, json};
+    use rst_common::with_tokio::tokio;
+
+    async fn sum((a, b): (i64, i64)) -> Result<i64, RpcError> {
+        Ok(a + b)
+    }
+
+    async fn hello() -> Result<String, RpcError> {
+        Ok(String::from("hello"))
+    }
+
+    async fn call<F, Args>(handler: F, params: Option<Value>) -> Result<String, RpcError>
+    where
+        F: HandlerFn<Args>,
+        Args: 'static,
+    {
+        let handler = FnHandler::new(handler);
+        let output = handler
+            .call(RpcContext::default(), Method::from("test.fn"), params)
+            .await?;
+        Ok(serde_json::to_string(output.unwrap().as_ref()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_async_fn_handler() {
+        let output = call(sum, Some(json!([1, 2]))).await;
+        assert_eq!(output.unwrap(), "3");
+
+        let output = call(hello, None).await;
+        assert_eq!(output.unwrap(), r#""hello""#);
+    }
+
+    #[tokio::test]
+    async fn test_closure_handler() {
+        let prefix = String::from("hello");
+        let handler = move |name: String| {
+            let prefix = prefix.clone();
+            async move { Ok::<_, RpcError>(format!("{} {}", prefix, name)) }
+        };
+
+        let output = call(handler, Some(json!("world"))).await;
+        assert_eq!(output.unwrap(), r#""hello world""#)
+    }
+
+    #[tokio::test]
+    async fn test_fn_handler_with_context() {
+        let handler = |ctx: RpcContext, name: String| async move {
+            let agent = ctx.header("x-agent").unwrap_or("unknown").to_string();
+            Ok::<_, RpcError>(format!("{} from {}", name, agent))
+        };
+
+        let mut ctx = RpcContext::default();
+        ctx.insert_header("X-Agent", "vessel");
+
+        let handler = FnHandler::new(handler);
+        let output = handler
+            .call(ctx, Method::from("test.fn"), Some(json!("ping")))
+            .await
+            .unwrap();
+
+        let jsonstr = serde_json::to_string(output.unwrap().as_ref()).unwrap();
+        assert_eq!(jsonstr, r#""ping from vessel""#)
+    }
+
+    #[tokio::test]
+    async fn test_fn_handler_invalid_params() {
+        let output = call(sum, Some(json!({"a": 1}))).await;
+        assert!(output.is_err());
+
+        let err = output.unwrap_err();
+        assert_eq!(err.build().0, RpcError::InvalidParams.build().0)
+    }
+}
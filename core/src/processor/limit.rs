@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rst_common::standard::serde_json::json;
+use rst_common::with_tokio::tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::types::{RpcError, RETRY_AFTER_KEY};
+
+/// `DEFAULT_RETRY_AFTER` is the default retry hint given to the rejected calls
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// `ConcurrencyLimit` used to limit how many calls can be executed at the same time
+///
+/// When all slots are in use, next calls will wait in a queue until its bound is reached.
+/// Any other calls will be rejected with [`RpcError::ServerBusy`], including for
+/// a `retry_after` hint, in seconds, as its `data`.
+///
+/// Its slots are shared by all of its clones, so the same limit can be applied to
+/// multiple routes to limit them as a group
+#[derive(Clone)]
+pub struct ConcurrencyLimit {
+    semaphore: Arc<Semaphore>,
+    queued: Arc<AtomicUsize>,
+    max_queue: usize,
+    retry_after: Duration,
+}
+
+impl ConcurrencyLimit {
+    /// `new` used to build the limit with given maximum in-flight calls, it will never
+    /// be lower than `1`. There is no queue by default
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_in_flight.max(1))),
+            queued: Arc::new(AtomicUsize::new(0)),
+            max_queue: 0,
+            retry_after: DEFAULT_RETRY_AFTER,
+        }
+    }
+
+    /// `with_queue` used to set how many calls may wait for a free slot
+    pub fn with_queue(mut self, max_queue: usize) -> Self {
+        self.max_queue = max_queue;
+        self
+    }
+
+    /// `with_retry_after` used to set the retry hint given to the rejected calls
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// `available` used to get current number of free slots
+    pub fn available(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+
+    /// `queued` used to get current number of calls waiting for a free slot
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    /// `acquire` used to take a slot, the slot will be released when the permit is dropped
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, RpcError> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        let queued = self.queued.fetch_add(1, Ordering::SeqCst);
+        let _queue = QueueGuard(self.queued.clone());
+        if queued >= self.max_queue {
+            return Err(self.busy());
+        }
+
+        self.semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| self.busy())
+    }
+
+    fn busy(&self) -> RpcError {
+        let retry_after = self.retry_after.as_millis().div_ceil(1000);
+        RpcError::ServerBusy.with_data(json!({ RETRY_AFTER_KEY: retry_after }))
+    }
+}
+
+struct QueueGuard(Arc<AtomicUsize>);
+
+impl Drop for QueueGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rst_common::with_tokio::tokio;
+
+    use crate::objects::RpcErrorBuilder;
+
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let limit = ConcurrencyLimit::new(1)
+            .with_queue(1)
+            .with_retry_after(Duration::from_millis(1500));
+
+        let permit = limit.acquire().await;
+        assert!(permit.is_ok());
+        assert_eq!(limit.available(), 0);
+
+        let queued = tokio::spawn({
+            let limit = limit.clone();
+            async move { limit.acquire().await.map(|_| ()) }
+        });
+
+        while limit.queued.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let rejected = limit.acquire().await;
+        assert!(rejected.is_err());
+
+        let err_obj = RpcErrorBuilder::build(rejected.err().unwrap());
+        assert_eq!(err_obj.code, -32002);
+        assert_eq!(err_obj.data, Some(json!({"retry_after": 2})));
+
+        drop(permit);
+        assert!(queued.await.unwrap().is_ok());
+        assert_eq!(limit.available(), 1);
+        assert_eq!(limit.queued.load(Ordering::SeqCst), 0)
+    }
+}
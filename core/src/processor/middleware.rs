@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use rst_common::standard::async_trait::async_trait;
+use rst_common::standard::serde_json::Value;
+
+use crate::objects::{RpcContext, RpcRequest, RpcResponse};
+use crate::types::RpcError;
+
+/// `MiddlewareBoxed` is an alias type used as shortcut to the shared middleware type
+pub type MiddlewareBoxed = Arc<dyn Middleware + Send + Sync>;
+
+/// `Middleware` is a trait used to wrap the handler dispatch with any cross-cutting
+/// concerns, like authentication, logging or metrics
+///
+/// A middleware may be applied globally through [`crate::objects::RpcProcessor::middleware`]
+/// or only to a single route through [`crate::types::RpcRoute::with_middleware`].
+/// Global middlewares always run before the route middlewares, and the `after` hooks
+/// will run in the reverse order of the `before` hooks
+#[async_trait]
+pub trait Middleware {
+    /// `before` will be called before the handler is executed
+    ///
+    /// The given [`RpcContext`] is mutable, so it can be used to provide any values
+    /// to the next middlewares and the handler. Returning an error will short-circuit
+    /// the dispatch, the handler and the next middlewares will not be called, and the
+    /// error will be used as the response
+    async fn before(&self, _ctx: &mut RpcContext, _request: &RpcRequest) -> Result<(), RpcError> {
+        Ok(())
+    }
+
+    /// `after` will be called after the handler, or the short-circuit error, has been
+    /// built into the [`RpcResponse`]
+    ///
+    /// It will only be called if its own `before` hook has been succeeded
+    async fn after(
+        &self,
+        _ctx: &RpcContext,
+        _request: &RpcRequest,
+        _response: &mut RpcResponse<Value>,
+    ) {
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rst_common::with_tokio::tokio;
+
+    use crate::types::RpcId;
+
+    #[derive(Clone)]
+    struct FakeMiddleware;
+
+    #[async_trait]
+    impl Middleware for FakeMiddleware {}
+
+    #[tokio::test]
+    async fn test_default_hooks() {
+        let middleware: MiddlewareBoxed = Arc::new(FakeMiddleware);
+        let request = RpcRequest {
+            jsonrpc: String::from("2.0"),
+            method: String::from("test.method"),
+            params: None,
+            id: Some(RpcId::IntegerVal(1)),
+        };
+
+        let mut ctx = RpcContext::default();
+        assert!(middleware.before(&mut ctx, &request).await.is_ok());
+
+        let mut response = RpcResponse::with_success(None, request.id.clone());
+        middleware.after(&ctx, &request, &mut response).await;
+        assert!(response.error.is_none())
+    }
+}
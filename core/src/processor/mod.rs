@@ -1,7 +1,17 @@
 mod base;
+mod func;
+mod limit;
+mod middleware;
+mod ratelimit;
+mod registry;
 mod rpc;
+#[cfg(feature = "tower")]
+mod service;
+mod typed;
 
-pub use rpc::RpcProcessor;
+pub use ratelimit::RateLimiter as RpcRateLimiter;
+pub use registry::RpcRegistry;
+pub use rpc::{RpcProcessor, DEFAULT_BATCH_CONCURRENCY, FALLBACK_ROUTE};
 
 pub mod types {
     use super::*;
@@ -13,4 +23,18 @@ pub mod types {
     pub use base::Method as RpcMethod;
     pub use base::ResponseSerialized as RpcResponseSerialized;
     pub use base::Route as RpcRoute;
+    pub use func::FnHandler as RpcFnHandler;
+    pub use func::HandlerFn as RpcHandlerFn;
+    pub use limit::ConcurrencyLimit as RpcConcurrencyLimit;
+    pub use limit::DEFAULT_RETRY_AFTER;
+    pub use middleware::Middleware as RpcMiddleware;
+    pub use middleware::MiddlewareBoxed as RpcMiddlewareBoxed;
+    pub use ratelimit::Clock as RpcClock;
+    pub use ratelimit::KeyExtractor as RpcKeyExtractor;
+    pub use ratelimit::Quota as RpcQuota;
+    pub use ratelimit::RateLimitKey as RpcRateLimitKey;
+    pub use ratelimit::SystemClock as RpcSystemClock;
+    pub use typed::parse_params;
+    pub use typed::TypedAdapter as RpcTypedAdapter;
+    pub use typed::TypedHandler as RpcTypedHandler;
 }
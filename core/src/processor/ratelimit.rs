@@ -0,0 +1,456 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rst_common::standard::async_trait::async_trait;
+use rst_common::standard::serde_json::json;
+
+use crate::objects::{RpcContext, RpcRequest};
+use crate::types::{RpcError, RpcMethod, RpcMiddleware, RESET_AFTER_KEY};
+
+/// `DEFAULT_MAX_BUCKETS` is the default maximum number of tracked buckets
+const DEFAULT_MAX_BUCKETS: usize = 10_000;
+
+/// `Clock` used by the [`RateLimiter`] to measure the elapsed time, it makes
+/// the limiter testable without waiting for the real time
+pub trait Clock: Send + Sync {
+    /// `now` returns the elapsed time since any fixed point of time
+    fn now(&self) -> Duration;
+}
+
+/// `SystemClock` is the default [`Clock`] based on the monotonic system time
+#[derive(Clone)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// `KeyExtractor` is an alias type used as shortcut to the custom client key extractor
+pub type KeyExtractor = Arc<dyn Fn(&RpcContext, &RpcRequest) -> Option<String> + Send + Sync>;
+
+/// `RateLimitKey` used to decide the client identity of a request
+///
+/// The requests without any identity will share the same anonymous bucket
+#[derive(Clone)]
+pub enum RateLimitKey {
+    /// Use the IP address of the remote peer
+    RemoteAddr,
+
+    /// Use the value of given header name, like an API key header
+    Header(String),
+
+    /// Use a custom extractor
+    Custom(KeyExtractor),
+}
+
+impl RateLimitKey {
+    pub fn custom<F>(f: F) -> Self
+    where
+        F: Fn(&RpcContext, &RpcRequest) -> Option<String> + Send + Sync + 'static,
+    {
+        RateLimitKey::Custom(Arc::new(f))
+    }
+
+    pub fn extract(&self, ctx: &RpcContext, request: &RpcRequest) -> Option<String> {
+        match self {
+            RateLimitKey::RemoteAddr => ctx.remote_addr().map(|addr| addr.ip().to_string()),
+            RateLimitKey::Header(name) => ctx.header(name).map(String::from),
+            RateLimitKey::Custom(extractor) => extractor(ctx, request),
+        }
+    }
+}
+
+/// `Quota` is the token bucket configuration, a client may burst up to `capacity` calls,
+/// and its tokens will be refilled continuously, `capacity` tokens for each `period`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quota {
+    capacity: u64,
+    period: Duration,
+}
+
+impl Quota {
+    /// `new` used to build the quota, the capacity will never be lower than `1`
+    pub fn new(capacity: u64, period: Duration) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            period,
+        }
+    }
+
+    pub fn per_second(capacity: u64) -> Self {
+        Self::new(capacity, Duration::from_secs(1))
+    }
+
+    pub fn per_minute(capacity: u64) -> Self {
+        Self::new(capacity, Duration::from_secs(60))
+    }
+
+    fn refill_rate(&self) -> f64 {
+        self.capacity as f64 / self.period.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// `BucketKey` is the bucket scope, the method name or empty for the default quota,
+/// and the client identity
+type BucketKey = (String, String);
+
+struct Bucket {
+    tokens: f64,
+    updated: Duration,
+    used: u64,
+}
+
+impl Bucket {
+    fn available(&self, quota: &Quota, now: Duration) -> f64 {
+        let elapsed = now.saturating_sub(self.updated).as_secs_f64();
+        (self.tokens + elapsed * quota.refill_rate()).min(quota.capacity as f64)
+    }
+
+    fn refill(&mut self, quota: &Quota, now: Duration) {
+        self.tokens = self.available(quota, now);
+        self.updated = now;
+    }
+}
+
+/// `Buckets` keeps the buckets in their least recently used order, so the oldest buckets
+/// can be found without scanning all of them
+#[derive(Default)]
+struct Buckets {
+    entries: HashMap<BucketKey, Bucket>,
+    order: BTreeMap<u64, BucketKey>,
+    last_used: u64,
+}
+
+impl Buckets {
+    /// `take` used to get the bucket of given key and mark it as the most recently used,
+    /// a new bucket will evict the least recently used buckets when the limit is reached
+    fn take(&mut self, key: BucketKey, quota: &Quota, now: Duration, max: usize) -> &mut Bucket {
+        self.last_used += 1;
+        match self.entries.get_mut(&key) {
+            Some(bucket) => {
+                self.order.remove(&bucket.used);
+            }
+            None => {
+                while self.entries.len() >= max {
+                    match self.order.pop_first() {
+                        Some((_, oldest)) => self.entries.remove(&oldest),
+                        None => break,
+                    };
+                }
+            }
+        }
+
+        self.order.insert(self.last_used, key.clone());
+        let bucket = self.entries.entry(key).or_insert(Bucket {
+            tokens: quota.capacity as f64,
+            updated: now,
+            used: 0,
+        });
+
+        bucket.used = self.last_used;
+        bucket
+    }
+
+    /// `sweep` used to drop the least recently used buckets which have been refilled,
+    /// it stops at the first bucket which still has used tokens
+    fn sweep<F>(&mut self, now: Duration, quota: F)
+    where
+        F: Fn(&str) -> Quota,
+    {
+        while let Some((_, key)) = self.order.first_key_value() {
+            let quota = quota(&key.0);
+            let refilled = self.entries.get(key).map_or(true, |bucket| {
+                bucket.available(&quota, now) >= quota.capacity as f64
+            });
+
+            if !refilled {
+                break;
+            }
+
+            if let Some((_, key)) = self.order.pop_first() {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+/// `RateLimiter` is a token bucket rate limiter [`RpcMiddleware`], keyed by the client identity
+///
+/// Each client has its own bucket for the default quota, which is shared by all methods
+/// without their own quota, and one bucket for each method which has its own quota.
+/// The limited requests will be answered with [`RpcError::RateLimited`] including for
+/// the `limit`, `remaining` tokens and `reset_after`, in seconds, as its `data`.
+///
+/// The refilled buckets are dropped starting from the least recently used ones, and the
+/// number of tracked buckets is capped, so the clients can't grow it without bound. When
+/// the cap is reached, the least recently used buckets will be evicted, which means their
+/// clients will start again with full buckets.
+///
+/// It can be applied globally to the processor, or to a single route
+#[derive(Clone)]
+pub struct RateLimiter {
+    key: RateLimitKey,
+    quota: Quota,
+    method_quotas: HashMap<RpcMethod, Quota>,
+    clock: Arc<dyn Clock>,
+    max_buckets: usize,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+    pub fn new(key: RateLimitKey, quota: Quota) -> Self {
+        Self {
+            key,
+            quota,
+            method_quotas: HashMap::new(),
+            clock: Arc::new(SystemClock::default()),
+            max_buckets: DEFAULT_MAX_BUCKETS,
+            buckets: Arc::new(Mutex::new(Buckets::default())),
+        }
+    }
+
+    /// `with_method_quota` used to give a method its own quota and buckets
+    pub fn with_method_quota(mut self, method: &str, quota: Quota) -> Self {
+        self.method_quotas.insert(RpcMethod::from(method), quota);
+        self
+    }
+
+    /// `with_max_buckets` used to change the maximum number of tracked buckets, it will
+    /// never be lower than `1`
+    pub fn with_max_buckets(mut self, max_buckets: usize) -> Self {
+        self.max_buckets = max_buckets.max(1);
+        self
+    }
+
+    pub fn with_clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// `check` used to take a token of the client's bucket for given method
+    pub fn check(&self, client: &str, method: &RpcMethod) -> Result<u64, RpcError> {
+        let (scope, quota) = match self.method_quotas.get(method) {
+            Some(quota) => (method.to_string(), *quota),
+            None => (String::new(), self.quota),
+        };
+
+        let now = self.clock.now();
+        let mut buckets = self
+            .buckets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        buckets.sweep(now, |scope| self.quota_of(scope));
+        let bucket = buckets.take((scope, client.to_string()), &quota, now, self.max_buckets);
+
+        bucket.refill(&quota, now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(bucket.tokens.floor() as u64);
+        }
+
+        let reset_after = ((1.0 - bucket.tokens) * quota.period.as_secs_f64()
+            / quota.capacity as f64)
+            .ceil() as u64;
+        Err(RpcError::RateLimited.with_data(json!({
+            "limit": quota.capacity,
+            "remaining": 0,
+            RESET_AFTER_KEY: reset_after.max(1),
+        })))
+    }
+
+    fn quota_of(&self, scope: &str) -> Quota {
+        if scope.is_empty() {
+            return self.quota;
+        }
+
+        self.method_quotas
+            .get(&RpcMethod::from(scope))
+            .copied()
+            .unwrap_or(self.quota)
+    }
+}
+
+#[async_trait]
+impl RpcMiddleware for RateLimiter {
+    async fn before(&self, ctx: &mut RpcContext, request: &RpcRequest) -> Result<(), RpcError> {
+        let client = self.key.extract(ctx, request).unwrap_or_default();
+        let method = RpcMethod::from(request.method.as_str());
+        self.check(&client, &method).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use rst_common::standard::serde_json::Value;
+    use rst_common::with_tokio::tokio;
+
+    use crate::objects::{RpcErrorBuilder, RpcProcessor};
+
+    #[derive(Clone, Default)]
+    struct FakeClock(Arc<AtomicU64>);
+
+    impl FakeClock {
+        fn advance(&self, duration: Duration) {
+            self.0
+                .fetch_add(duration.as_millis() as u64, Ordering::SeqCst);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Duration {
+            Duration::from_millis(self.0.load(Ordering::SeqCst))
+        }
+    }
+
+    fn rejected_data(result: Result<u64, RpcError>) -> Value {
+        RpcErrorBuilder::build(result.err().unwrap()).data.unwrap()
+    }
+
+    #[test]
+    fn test_rate_limiter_buckets() {
+        let clock = FakeClock::default();
+        let limiter = RateLimiter::new(RateLimitKey::RemoteAddr, Quota::per_second(2))
+            .with_method_quota("expensive", Quota::per_minute(1))
+            .with_clock(clock.clone());
+
+        let method = RpcMethod::from("cheap");
+        assert_eq!(limiter.check("alice", &method).unwrap(), 1);
+        assert_eq!(limiter.check("alice", &method).unwrap(), 0);
+        assert_eq!(
+            rejected_data(limiter.check("alice", &method)),
+            json!({"limit": 2, "remaining": 0, "reset_after": 1})
+        );
+        assert!(limiter.check("bob", &method).is_ok());
+        assert!(limiter.check("alice", &RpcMethod::from("other")).is_err());
+
+        let expensive = RpcMethod::from("expensive");
+        assert!(limiter.check("alice", &expensive).is_ok());
+        assert_eq!(
+            rejected_data(limiter.check("alice", &expensive)),
+            json!({"limit": 1, "remaining": 0, "reset_after": 60})
+        );
+
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(limiter.check("alice", &method).unwrap(), 0);
+        assert!(limiter.check("alice", &method).is_err());
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(limiter.check("alice", &method).unwrap(), 1);
+        assert_eq!(
+            rejected_data(limiter.check("alice", &expensive)),
+            json!({"limit": 1, "remaining": 0, "reset_after": 50})
+        )
+    }
+
+    #[test]
+    fn test_rate_limiter_max_buckets() {
+        let clock = FakeClock::default();
+        let limiter = RateLimiter::new(RateLimitKey::RemoteAddr, Quota::per_minute(1))
+            .with_max_buckets(2)
+            .with_clock(clock.clone());
+
+        let tracked = || {
+            let buckets = limiter.buckets.lock().unwrap();
+            let mut clients: Vec<String> = buckets
+                .entries
+                .keys()
+                .map(|(_, client)| client.clone())
+                .collect();
+
+            clients.sort();
+            clients
+        };
+
+        let method = RpcMethod::from("test");
+        assert!(limiter.check("alice", &method).is_ok());
+        assert!(limiter.check("bob", &method).is_ok());
+        assert!(limiter.check("alice", &method).is_err());
+
+        assert!(limiter.check("carol", &method).is_ok());
+        assert_eq!(tracked(), vec!["alice", "carol"]);
+        assert!(limiter.check("alice", &method).is_err());
+
+        assert!(limiter.check("bob", &method).is_ok());
+        assert_eq!(tracked(), vec!["alice", "bob"]);
+
+        clock.advance(Duration::from_secs(60));
+        assert!(limiter.check("dave", &method).is_ok());
+        assert_eq!(tracked(), vec!["dave"])
+    }
+
+    #[test]
+    fn test_rate_limit_keys() {
+        let request = RpcRequest {
+            jsonrpc: String::from("2.0"),
+            method: String::from("test"),
+            params: None,
+            id: None,
+        };
+
+        let mut ctx = RpcContext::new();
+        ctx.set_remote_addr("10.0.0.1:4000".parse().unwrap())
+            .insert_header("X-Api-Key", "secret");
+
+        let table = vec![
+            (RateLimitKey::RemoteAddr, Some(String::from("10.0.0.1"))),
+            (
+                RateLimitKey::Header(String::from("x-api-key")),
+                Some(String::from("secret")),
+            ),
+            (RateLimitKey::Header(String::from("x-unknown")), None),
+            (
+                RateLimitKey::custom(|_, request| Some(request.method.clone())),
+                Some(String::from("test")),
+            ),
+        ];
+
+        for (key, expected) in table {
+            assert_eq!(key.extract(&ctx, &request), expected)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_middleware() {
+        let mut processor = RpcProcessor::default();
+        processor.middleware(
+            RateLimiter::new(RateLimitKey::RemoteAddr, Quota::per_minute(1))
+                .with_clock(FakeClock::default()),
+        );
+
+        let mut ctx = RpcContext::new();
+        ctx.set_remote_addr("10.0.0.1:4000".parse().unwrap());
+
+        let body = br#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1}"#;
+        let table = vec![
+            r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":1}"#,
+            r#"{"jsonrpc":"2.0","error":{"code":-32003,"message":"Rate limit exceeded","data":{"limit":1,"remaining":0,"reset_after":60}},"id":1}"#,
+        ];
+
+        for expected in table {
+            let response = processor.execute_bytes(ctx.clone(), body).await;
+            let jsonstr = rst_common::standard::serde_json::to_string(&response.unwrap());
+            assert_eq!(expected, jsonstr.unwrap())
+        }
+    }
+}
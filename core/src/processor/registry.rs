@@ -0,0 +1,192 @@
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+use arc_swap::ArcSwap;
+
+use crate::objects::RpcProcessor;
+use crate::types::{RpcHandlerFn, RpcMethod, RpcRoute};
+
+/// `RpcRegistry` is a hot-swappable [`RpcProcessor`] which can be changed while
+/// the server is running
+///
+/// Each change will build a new copy of current processor and swap it atomically, so
+/// the readers never need to take any locks. A request which already loaded the processor
+/// will always finish using the same snapshot, while the next requests will use the
+/// latest one. The changes themselves are serialized, so they will never overwrite
+/// each other.
+///
+/// The registry is cheap to clone, all of its clones share the same processor
+#[derive(Clone)]
+pub struct RpcRegistry {
+    current: Arc<ArcSwap<RpcProcessor>>,
+    writer: Arc<Mutex<()>>,
+}
+
+impl RpcRegistry {
+    pub fn new(processor: RpcProcessor) -> Self {
+        Self::from_shared(Arc::new(processor))
+    }
+
+    pub fn from_shared(processor: Arc<RpcProcessor>) -> Self {
+        Self {
+            current: Arc::new(ArcSwap::new(processor)),
+            writer: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// `load` used to get the snapshot of current processor
+    pub fn load(&self) -> Arc<RpcProcessor> {
+        self.current.load_full()
+    }
+
+    /// `update` used to change current processor using given function
+    ///
+    /// If the function returns an error, current processor will not be changed
+    pub fn update<F, E>(&self, f: F) -> Result<(), E>
+    where
+        F: FnOnce(&mut RpcProcessor) -> Result<(), E>,
+    {
+        let _guard = self
+            .writer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let mut processor = RpcProcessor::clone(&self.current.load());
+        f(&mut processor)?;
+
+        self.current.store(Arc::new(processor));
+        Ok(())
+    }
+
+    /// `replace` used to replace current processor entirely
+    pub fn replace(&self, processor: RpcProcessor) -> &Self {
+        let _guard = self
+            .writer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        self.current.store(Arc::new(processor));
+        self
+    }
+
+    /// `register_route` used to register or replace the route of a live processor
+    pub fn register_route(&self, route: RpcRoute) -> &Self {
+        self.update::<_, Infallible>(|processor| {
+            processor.register_route(route);
+            Ok(())
+        })
+        .unwrap_or_else(|never| match never {});
+
+        self
+    }
+
+    /// `method` used to register or replace a function handler of a live processor
+    pub fn method<F, Args>(&self, method: &str, handler: F) -> &Self
+    where
+        F: RpcHandlerFn<Args>,
+        Args: 'static,
+    {
+        self.update::<_, Infallible>(|processor| {
+            processor.method(method, handler);
+            Ok(())
+        })
+        .unwrap_or_else(|never| match never {});
+
+        self
+    }
+
+    /// `unregister` used to remove the route of given method from a live processor
+    ///
+    /// It will return `false` if the method is not registered
+    pub fn unregister(&self, method: &RpcMethod) -> bool {
+        self.update(|processor| match processor.unregister(method) {
+            Some(_) => Ok(()),
+            None => Err(()),
+        })
+        .is_ok()
+    }
+}
+
+impl From<RpcProcessor> for RpcRegistry {
+    fn from(processor: RpcProcessor) -> Self {
+        RpcRegistry::new(processor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rst_common::standard::serde_json;
+    use rst_common::with_tokio::tokio;
+
+    use crate::objects::RpcContext;
+    use crate::types::{RpcError, RpcRouterError};
+
+    async fn call(registry: &RpcRegistry, method: &str) -> String {
+        let request = format!(r#"{{"jsonrpc":"2.0","method":"{}","id":1}}"#, method);
+        let response = registry
+            .load()
+            .execute_bytes(RpcContext::default(), request.as_bytes())
+            .await;
+
+        serde_json::to_string(&response.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_registry_changes() {
+        let registry = RpcRegistry::new(RpcProcessor::new());
+        let snapshot = registry.load();
+
+        registry.method(
+            "plugin.hello",
+            |_: ()| async move { Ok::<_, RpcError>("hello") },
+        );
+        assert_eq!(
+            call(&registry, "plugin.hello").await,
+            r#"{"jsonrpc":"2.0","result":"hello","id":1}"#
+        );
+        assert!(snapshot.handlers().is_empty());
+
+        registry.clone().method("plugin.hello", |_: ()| async move {
+            Ok::<_, RpcError>("replaced")
+        });
+        assert_eq!(
+            call(&registry, "plugin.hello").await,
+            r#"{"jsonrpc":"2.0","result":"replaced","id":1}"#
+        );
+
+        assert!(registry.unregister(&RpcMethod::from("plugin.hello")));
+        assert!(!registry.unregister(&RpcMethod::from("plugin.hello")));
+        assert_eq!(
+            call(&registry, "plugin.hello").await,
+            r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":1}"#
+        );
+
+        registry.replace(RpcProcessor::default());
+        assert_eq!(registry.load().handlers().len(), 1)
+    }
+
+    #[tokio::test]
+    async fn test_registry_update_error() {
+        let registry = RpcRegistry::from(RpcProcessor::default());
+
+        let mut plugin = RpcProcessor::new();
+        plugin.method("ping", |_: ()| async move { Ok::<_, RpcError>("pong") });
+
+        let result = registry.update(|processor| {
+            processor
+                .method("plugin.other", |_: ()| async move { Ok::<_, RpcError>(()) })
+                .nest("prople.vessel", plugin)?;
+            Ok::<_, RpcRouterError>(())
+        });
+
+        assert_eq!(
+            result,
+            Err(RpcRouterError::DuplicateMethod(String::from(
+                "prople.vessel.ping"
+            )))
+        );
+        assert_eq!(registry.load().handlers().len(), 1)
+    }
+}
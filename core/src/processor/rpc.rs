@@ -1,40 +1,351 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
 
+use rst_common::standard::futures::stream::{self, StreamExt};
+use rst_common::standard::serde_json::{self, json, Value};
 use rst_common::with_logging::log::error;
+use rst_common::with_tokio::tokio;
 
 use crate::handlers::{AgentPingHandler, PING_RPC_METHOD};
-use crate::objects::{RpcErrorBuilder, RpcRequest, RpcResponse};
-use crate::types::{RpcError, RpcHandlerBoxed, RpcMethod, RpcResponseSerialized, RpcRoute};
+use crate::objects::{
+    RpcContext, RpcErrorBuilder, RpcErrorCatalog, RpcPayload, RpcPayloadResponse, RpcPrincipal,
+    RpcRequest, RpcResponse, RpcStateBoxed,
+};
+use crate::types::{
+    RpcConcurrencyLimit, RpcError, RpcFnHandler, RpcHandlerBoxed, RpcHandlerFn, RpcMethod,
+    RpcMiddleware, RpcMiddlewareBoxed, RpcRoute, RpcRouterError,
+};
+
+/// `DEFAULT_BATCH_CONCURRENCY` is the default maximum number of batch elements
+/// executed at the same time
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 16;
+
+/// `FALLBACK_ROUTE` is the key of the fallback handler in [`RpcProcessor::scopes`]
+pub const FALLBACK_ROUTE: &str = "*";
+
+/// `RouteOptions` keeps the options of a registered route, besides its handler
+#[derive(Clone, Default)]
+struct RouteOptions {
+    middlewares: Vec<RpcMiddlewareBoxed>,
+    timeout: Option<Duration>,
+    concurrency_limit: Option<RpcConcurrencyLimit>,
+    scopes: Vec<String>,
+}
+
+impl From<&RpcRoute> for RouteOptions {
+    fn from(route: &RpcRoute) -> Self {
+        Self {
+            middlewares: route.middlewares(),
+            timeout: route.timeout(),
+            concurrency_limit: route.concurrency_limit(),
+            scopes: route.scopes(),
+        }
+    }
+}
+
+impl RouteOptions {
+    /// `authorize` used to check the required scopes against the caller principal
+    fn authorize(&self, ctx: &RpcContext) -> Result<(), RpcError> {
+        if self.scopes.is_empty() {
+            return Ok(());
+        }
+
+        let principal = ctx.principal().ok_or(RpcError::Unauthorized)?;
+        let missing: Vec<&String> = self
+            .scopes
+            .iter()
+            .filter(|scope| !principal.has_scope(scope))
+            .collect();
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        Err(RpcError::Forbidden.with_data(json!({
+            "required": self.scopes,
+            "missing": missing,
+        })))
+    }
+}
 
 #[derive(Clone)]
 /// `RpcProcessor` is primary object to manage request method handlers including
 /// for its handler execution
 pub struct RpcProcessor {
     handlers: HashMap<RpcMethod, RpcHandlerBoxed>,
+    middlewares: Vec<RpcMiddlewareBoxed>,
+    routes: HashMap<RpcMethod, RouteOptions>,
+    fallback: Option<RpcHandlerBoxed>,
+    fallback_options: RouteOptions,
+    timeout: Option<Duration>,
+    concurrency_limit: Option<RpcConcurrencyLimit>,
+    batch_concurrency: usize,
+    state: Option<RpcStateBoxed>,
+    error_catalog: Arc<RpcErrorCatalog>,
 }
 
 impl Default for RpcProcessor {
     fn default() -> Self {
-        let mut handlers: HashMap<RpcMethod, RpcHandlerBoxed> = HashMap::new();
-        handlers.insert(RpcMethod::from(PING_RPC_METHOD), Box::new(AgentPingHandler));
+        let mut processor = RpcProcessor::new();
+        processor
+            .handlers
+            .insert(RpcMethod::from(PING_RPC_METHOD), Box::new(AgentPingHandler));
 
-        RpcProcessor { handlers }
+        processor
     }
 }
 
 impl RpcProcessor {
     pub fn new() -> Self {
         let handlers: HashMap<RpcMethod, RpcHandlerBoxed> = HashMap::new();
-        Self { handlers }
+        Self {
+            handlers,
+            middlewares: Vec::new(),
+            routes: HashMap::new(),
+            fallback: None,
+            fallback_options: RouteOptions::default(),
+            timeout: None,
+            concurrency_limit: None,
+            batch_concurrency: DEFAULT_BATCH_CONCURRENCY,
+            state: None,
+            error_catalog: Arc::new(RpcErrorCatalog::default()),
+        }
+    }
+
+    /// `set_state` used to register the shared application state
+    ///
+    /// The same state will be provided to each handler call through the [`RpcContext`],
+    /// so the handlers don't need to carry their own clones of any shared services.
+    /// It can be extracted using [`RpcContext::state`] or the [`RpcSharedState`] extractor
+    pub fn set_state<S>(&mut self, state: Arc<S>) -> &mut Self
+    where
+        S: Send + Sync + 'static,
+    {
+        self.state = Some(state);
+        self
+    }
+
+    /// `middleware` used to apply given middleware globally to all methods
+    ///
+    /// Global middlewares will be executed in their registration order, before any
+    /// route middlewares. They will also be executed for unknown methods
+    pub fn middleware<M>(&mut self, middleware: M) -> &mut Self
+    where
+        M: RpcMiddleware + Send + Sync + 'static,
+    {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// `set_timeout` used to set the default timeout of all handlers
+    ///
+    /// A call which doesn't finish in time, including for its middlewares, will be cancelled
+    /// and answered with [`RpcError::RequestTimeout`]. Each route may override it using
+    /// [`RpcRoute::with_timeout`]. There is no timeout by default
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// `set_concurrency_limit` used to limit the in-flight calls of all handlers
+    ///
+    /// A call must take a slot of its own route limit first, before taking a slot of the
+    /// global limit, so the calls queued by a route never hold any global slots.
+    /// The rejected calls will be answered with [`RpcError::ServerBusy`], while the time
+    /// spent in the queue will be counted into the call timeout
+    pub fn set_concurrency_limit(&mut self, limit: RpcConcurrencyLimit) -> &mut Self {
+        self.concurrency_limit = Some(limit);
+        self
+    }
+
+    /// `set_batch_concurrency` used to limit how many batch elements will be executed
+    /// at the same time
+    ///
+    /// The given limit will never be lower than `1`, which means all batch elements
+    /// will be executed sequentially
+    pub fn set_batch_concurrency(&mut self, limit: usize) -> &mut Self {
+        self.batch_concurrency = limit.max(1);
+        self
+    }
+
+    /// `batch_concurrency` used to get current batch concurrency limit
+    pub fn batch_concurrency(&self) -> usize {
+        self.batch_concurrency
+    }
+
+    /// `set_error_catalog` used to register the [`RpcErrorCatalog`] of this processor
+    ///
+    /// Its messages will be used by any [`RpcError::Custom`] without message returned by
+    /// the handlers or middlewares
+    pub fn set_error_catalog(&mut self, catalog: RpcErrorCatalog) -> &mut Self {
+        self.error_catalog = Arc::new(catalog);
+        self
+    }
+
+    /// `error_catalog` used to get current error catalog, it can be listed to generate
+    /// an API documentation
+    pub fn error_catalog(&self) -> &RpcErrorCatalog {
+        &self.error_catalog
     }
 
     /// `register_controller` used to register given [`RpcController`] to the current registry
+    ///
+    /// A method ending with `.*`, like `prople.vessel.*`, will be registered as a pattern
+    /// route. It will match any methods under its prefix which don't have their own exact
+    /// route, and the longest matched pattern will always be used. The handler will
+    /// receive the full requested [`RpcMethod`]
+    ///
+    /// Registering the same method twice will replace its handler and all of its options
     pub fn register_route(&mut self, route: RpcRoute) -> &mut Self {
-        let controller = route.controller();
-        self.handlers.insert(route.method(), controller);
+        let method = route.method();
+        let options = RouteOptions::from(&route);
+
+        self.handlers.insert(method.clone(), route.controller());
+        self.routes.insert(method, options);
         self
     }
 
+    /// `unregister` used to remove the route of given method, including its options
+    ///
+    /// The method must be the same as the registered one, including for the pattern routes
+    pub fn unregister(&mut self, method: &RpcMethod) -> Option<RpcHandlerBoxed> {
+        self.routes.remove(method);
+        self.handlers.remove(method)
+    }
+
+    /// `set_fallback` used to register the handler for any unmatched methods
+    ///
+    /// Without a fallback, an unmatched method will be answered with
+    /// [`RpcError::MethodNotFound`]. Only the global middlewares will be applied to it,
+    /// and its required scopes can be set using [`RpcProcessor::set_fallback_scopes`]
+    pub fn set_fallback(&mut self, handler: RpcHandlerBoxed) -> &mut Self {
+        self.fallback = Some(handler);
+        self
+    }
+
+    /// `set_fallback_scopes` used to require the caller of the fallback handler to have
+    /// all of given scopes
+    pub fn set_fallback_scopes<S: ToString>(&mut self, scopes: &[S]) -> &mut Self {
+        self.fallback_options.scopes = scopes.iter().map(|scope| scope.to_string()).collect();
+        self
+    }
+
+    /// `fallback` used to register a plain async function or closure as the fallback handler
+    pub fn fallback<F, Args>(&mut self, handler: F) -> &mut Self
+    where
+        F: RpcHandlerFn<Args>,
+        Args: 'static,
+    {
+        self.set_fallback(Box::new(RpcFnHandler::new(handler)))
+    }
+
+    /// `nest` used to compose all methods of given processor under a prefix
+    ///
+    /// A method `ping` nested with the prefix `prople.vessel` will be registered as
+    /// `prople.vessel.ping`. The global middlewares of given processor will only be applied
+    /// to its own methods, and its default timeout will be used by its methods which don't have
+    /// their own timeout. Its global concurrency limit will be shared by its methods which don't
+    /// have their own limit. Its fallback, shared state, batch concurrency and error catalog
+    /// will be ignored.
+    ///
+    /// Unlike [`RpcProcessor::register_route`], it will never overwrite any registered
+    /// methods. If one of the methods is already registered, none of them will be registered
+    /// and [`RpcRouterError::DuplicateMethod`] will be returned
+    pub fn nest(
+        &mut self,
+        prefix: &str,
+        processor: RpcProcessor,
+    ) -> Result<&mut Self, RpcRouterError> {
+        if prefix.is_empty() || prefix.starts_with('.') || prefix.ends_with('.') {
+            return Err(RpcRouterError::InvalidPrefix(prefix.to_string()));
+        }
+
+        self.compose(processor, |method| {
+            RpcMethod::from(format!("{}.{}", prefix, method.to_string()))
+        })
+    }
+
+    /// `merge` used to compose all methods of given processor without any prefix
+    ///
+    /// It has the same rules with [`RpcProcessor::nest`]
+    pub fn merge(&mut self, processor: RpcProcessor) -> Result<&mut Self, RpcRouterError> {
+        self.compose(processor, |method| method.clone())
+    }
+
+    fn compose<F>(
+        &mut self,
+        processor: RpcProcessor,
+        rename: F,
+    ) -> Result<&mut Self, RpcRouterError>
+    where
+        F: Fn(&RpcMethod) -> RpcMethod,
+    {
+        let RpcProcessor {
+            handlers,
+            middlewares,
+            mut routes,
+            timeout,
+            concurrency_limit,
+            ..
+        } = processor;
+
+        let routes: Vec<(RpcMethod, RpcHandlerBoxed, RouteOptions)> = handlers
+            .into_iter()
+            .map(|(method, handler)| {
+                let options = routes.remove(&method).unwrap_or_default();
+                let mut route_middlewares = middlewares.clone();
+                route_middlewares.extend(options.middlewares);
+
+                let options = RouteOptions {
+                    middlewares: route_middlewares,
+                    timeout: options.timeout.or(timeout),
+                    concurrency_limit: options.concurrency_limit.or(concurrency_limit.clone()),
+                    scopes: options.scopes,
+                };
+
+                (rename(&method), handler, options)
+            })
+            .collect();
+
+        let duplicate = routes
+            .iter()
+            .filter(|(method, _, _)| self.handlers.contains_key(method))
+            .map(|(method, _, _)| method.to_string())
+            .min();
+
+        if let Some(method) = duplicate {
+            return Err(RpcRouterError::DuplicateMethod(method));
+        }
+
+        for (method, handler, options) in routes {
+            self.handlers.insert(method.clone(), handler);
+            self.routes.insert(method, options);
+        }
+
+        Ok(self)
+    }
+
+    /// `method` used to register a plain async function or closure as the handler
+    /// of given method name
+    ///
+    /// ```ignore
+    /// processor.method("math.sum", |(a, b): (i64, i64)| async move {
+    ///     Ok::<_, RpcError>(a + b)
+    /// });
+    /// ```
+    pub fn method<F, Args>(&mut self, method: &str, handler: F) -> &mut Self
+    where
+        F: RpcHandlerFn<Args>,
+        Args: 'static,
+    {
+        let route = RpcRoute::new(
+            RpcMethod::from(method),
+            Box::new(RpcFnHandler::new(handler)),
+        );
+        self.register_route(route)
+    }
+
     /// `handlers` used to get current saved hash map
     ///
     /// The return value will be in shared reference without any mutability capability
@@ -42,36 +353,290 @@ impl RpcProcessor {
         &self.handlers
     }
 
+    /// `scopes` used to get the required scopes of all registered methods, sorted by
+    /// their method names
+    ///
+    /// The methods which don't require any scopes will be listed with an empty scopes,
+    /// so it can be used to audit who can call what. The fallback handler, if any, will be
+    /// listed as [`FALLBACK_ROUTE`]
+    pub fn scopes(&self) -> BTreeMap<String, Vec<String>> {
+        let fallback = self.fallback.as_ref().map(|_| {
+            (
+                FALLBACK_ROUTE.to_string(),
+                self.fallback_options.scopes.clone(),
+            )
+        });
+
+        self.handlers
+            .keys()
+            .map(|method| {
+                let scopes = self
+                    .routes
+                    .get(method)
+                    .map(|options| options.scopes.clone())
+                    .unwrap_or_default();
+
+                (method.to_string(), scopes)
+            })
+            .chain(fallback)
+            .collect()
+    }
+
+    /// `allowed_methods` used to get the sorted registered methods which given principal
+    /// is allowed to call
+    pub fn allowed_methods(&self, principal: &RpcPrincipal) -> Vec<String> {
+        self.scopes()
+            .into_iter()
+            .filter(|(_, scopes)| scopes.iter().all(|scope| principal.has_scope(scope)))
+            .map(|(method, _)| method)
+            .collect()
+    }
+
     /// `execute` used to process incoming [`RpcRequest]
     ///
     /// The internal flow is, for each time incoming request object
     /// it will fetch the handler based on RPC method.
     /// If it have a handler, it will *call* the handler.
     /// If not, it will build the [`RpcErrorObject`] and put it into the [`RpcResponse`]
-    pub async fn execute(&self, request: RpcRequest) -> RpcResponse<RpcResponseSerialized> {
+    ///
+    /// A notification, a request without an `id`, will still be executed but its
+    /// response will be suppressed, which is why this method returns an [`Option`].
+    ///
+    /// The given [`RpcContext`] will be filled with current request `id` and `method`
+    /// before being passed to the handler
+    pub async fn execute(
+        &self,
+        ctx: RpcContext,
+        request: RpcRequest,
+    ) -> Option<RpcResponse<Value>> {
+        let is_notification = request.is_notification();
+        let response = self.dispatch(ctx, request).await;
+
+        if is_notification {
+            return None;
+        }
+
+        Some(response)
+    }
+
+    /// `execute_batch` used to process a batch of incoming requests
+    ///
+    /// Each element will be parsed into [`RpcRequest`] independently. A malformed element
+    /// will be answered with [`RpcError::InvalidRequest`] without affecting other elements.
+    /// Elements will be executed concurrently, limited by the batch concurrency, and
+    /// the responses will keep the same order with their requests.
+    ///
+    /// Notifications will not have any response, so the returned responses may be
+    /// less than given requests, or even empty. Each element will receive its own copy
+    /// of given [`RpcContext`]
+    pub async fn execute_batch(
+        &self,
+        ctx: RpcContext,
+        requests: Vec<Value>,
+    ) -> Vec<RpcResponse<Value>> {
+        stream::iter(requests)
+            .map(|value| self.execute_value(ctx.clone(), value))
+            .buffered(self.batch_concurrency)
+            .filter_map(|response| async move { response })
+            .collect()
+            .await
+    }
+
+    /// `execute_payload` used to process incoming [`RpcPayload`], which may be a single
+    /// request or a batch of requests
+    ///
+    /// An empty batch is not a valid request, based on the specification it must be answered
+    /// with a single [`RpcError::InvalidRequest`] response instead of an empty array.
+    ///
+    /// If the payload only contains notifications, there will be no response at all
+    pub async fn execute_payload(
+        &self,
+        ctx: RpcContext,
+        payload: RpcPayload,
+    ) -> Option<RpcPayloadResponse<Value>> {
+        match payload {
+            RpcPayload::Single(request) => self
+                .execute_value(ctx, request)
+                .await
+                .map(RpcPayloadResponse::Single),
+            RpcPayload::Batch(requests) if requests.is_empty() => {
+                let err_obj = self.error_catalog.build(RpcError::InvalidRequest);
+                Some(RpcPayloadResponse::Single(RpcResponse::with_error(
+                    Some(err_obj),
+                    None,
+                )))
+            }
+            RpcPayload::Batch(requests) => {
+                let responses = self.execute_batch(ctx, requests).await;
+                if responses.is_empty() {
+                    return None;
+                }
+
+                Some(RpcPayloadResponse::Batch(responses))
+            }
+        }
+    }
+
+    /// `execute_bytes` used to process raw request body
+    ///
+    /// The given bytes will be parsed and validated before being executed. An invalid JSON
+    /// will be answered with [`RpcError::ParseError`], while an invalid request object will
+    /// be answered with [`RpcError::InvalidRequest`], both of them will never be rejected
+    /// outside of the `JSON-RPC` response object
+    pub async fn execute_bytes(
+        &self,
+        ctx: RpcContext,
+        bytes: &[u8],
+    ) -> Option<RpcPayloadResponse<Value>> {
+        match RpcPayload::from_slice(bytes) {
+            Ok(payload) => self.execute_payload(ctx, payload).await,
+            Err(err) => {
+                error!("error parse request body: {}", err);
+                let err_obj = self.error_catalog.build(err);
+                Some(RpcPayloadResponse::Single(RpcResponse::with_error(
+                    Some(err_obj),
+                    None,
+                )))
+            }
+        }
+    }
+
+    async fn execute_value(&self, ctx: RpcContext, value: Value) -> Option<RpcResponse<Value>> {
+        match RpcRequest::from_value(value) {
+            Ok(request) => self.execute(ctx, request).await,
+            Err(err) => {
+                error!("error invalid request object: {}", err.error);
+                Some(err.into_response())
+            }
+        }
+    }
+
+    async fn dispatch(&self, ctx: RpcContext, request: RpcRequest) -> RpcResponse<Value> {
         let method = RpcMethod::from(request.method.clone());
+        let mut ctx = ctx.for_request(request.id.clone(), method.clone());
+        ctx.set_state(self.state.clone());
+
+        let route = self.resolve(&method);
+        let options = route.as_ref().and_then(|route| self.routes.get(route));
+        let timeout = options.and_then(|options| options.timeout).or(self.timeout);
+        let middlewares: Vec<&RpcMiddlewareBoxed> = self
+            .middlewares
+            .iter()
+            .chain(
+                options
+                    .into_iter()
+                    .flat_map(|options| options.middlewares.iter()),
+            )
+            .collect();
+
+        let cancellation = ctx.cancellation().clone();
+        let mut passed = 0;
+        let execution = async {
+            for middleware in middlewares.iter() {
+                if let Err(err) = middleware.before(&mut ctx, &request).await {
+                    error!("error from middleware: {}", err);
+                    let err_obj: RpcErrorBuilder = self.error_catalog.build(err);
+                    return RpcResponse::with_error(Some(err_obj), request.id.clone());
+                }
+
+                passed += 1;
+            }
+
+            self.call(ctx.clone(), method, route, &request).await
+        };
+
+        let mut response = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, execution)
+                .await
+                .unwrap_or_else(|_| {
+                    cancellation.cancel();
+                    error!("error from handler: {}", RpcError::RequestTimeout);
+                    let err_obj: RpcErrorBuilder =
+                        self.error_catalog.build(RpcError::RequestTimeout);
+                    RpcResponse::with_error(Some(err_obj), request.id.clone())
+                }),
+            None => execution.await,
+        };
+
+        for middleware in middlewares[..passed].iter().rev() {
+            middleware.after(&ctx, &request, &mut response).await;
+        }
+
+        response
+    }
+
+    /// `resolve` used to find the registered route of given method, it will try the exact
+    /// route first before trying the pattern routes from the longest prefix
+    fn resolve(&self, method: &RpcMethod) -> Option<RpcMethod> {
+        if self.handlers.contains_key(method) {
+            return Some(method.clone());
+        }
+
+        let name = method.to_string();
+        name.rmatch_indices('.')
+            .map(|(idx, _)| RpcMethod::from(format!("{}.*", &name[..idx])))
+            .find(|pattern| self.handlers.contains_key(pattern))
+    }
+
+    async fn call(
+        &self,
+        ctx: RpcContext,
+        method: RpcMethod,
+        route: Option<RpcMethod>,
+        request: &RpcRequest,
+    ) -> RpcResponse<Value> {
         let params = request.params.clone();
+        let found = route.as_ref().and_then(|route| {
+            self.handlers
+                .get(route)
+                .map(|handler| (handler, self.routes.get(route)))
+        });
+
+        let fallback = self
+            .fallback
+            .as_ref()
+            .map(|fallback| (fallback, Some(&self.fallback_options)));
 
-        let handler = match self.handlers.get(&method) {
+        let (handler, options) = match found.or(fallback) {
             Some(caller) => caller,
             None => {
-                let err_obj: RpcErrorBuilder =
-                    RpcErrorBuilder::build(RpcError::MethodNotFound);
-                let response = RpcResponse::with_error(Some(err_obj), request.id);
+                let err_obj: RpcErrorBuilder = self.error_catalog.build(RpcError::MethodNotFound);
+                let response = RpcResponse::with_error(Some(err_obj), request.id.clone());
                 return response;
             }
         };
 
-        match handler.call(method, params).await {
-            Ok(success) => {
-                let response = RpcResponse::with_success(success, request.id);
-                response
+        let limits: Vec<&RpcConcurrencyLimit> = options
+            .and_then(|options| options.concurrency_limit.as_ref())
+            .into_iter()
+            .chain(self.concurrency_limit.iter())
+            .collect();
+
+        if let Some(Err(err)) = options.map(|options| options.authorize(&ctx)) {
+            error!("error from authorization: {}", err);
+            let err_obj: RpcErrorBuilder = self.error_catalog.build(err);
+            return RpcResponse::with_error(Some(err_obj), request.id.clone());
+        }
+
+        let execution = async {
+            let mut permits = Vec::with_capacity(limits.len());
+            for limit in limits {
+                permits.push(limit.acquire().await?);
             }
+
+            let output = handler.call(ctx, method, params).await?;
+            output.map(serde_json::to_value).transpose().map_err(|err| {
+                error!("error serialize handler output: {}", err);
+                RpcError::InternalError
+            })
+        };
+
+        match execution.await {
+            Ok(success) => RpcResponse::with_success(success, request.id.clone()),
             Err(err) => {
-                error!("error from handler: {}", err.to_string());
-                let err_obj: RpcErrorBuilder = RpcErrorBuilder::build(err);
-                let response = RpcResponse::with_error(Some(err_obj), request.id);
-                response
+                error!("error from handler: {}", err);
+                let err_obj: RpcErrorBuilder = self.error_catalog.build(err);
+                RpcResponse::with_error(Some(err_obj), request.id.clone())
             }
         }
     }
@@ -84,19 +649,18 @@ mod tests {
     use mockall::*;
 
     use rst_common::standard::async_trait::async_trait;
-    use rst_common::standard::serde_json::{self, Value};
-
-    use rst_common::with_tokio::tokio;
+    use rst_common::standard::serde_json::{self, json};
 
-    use crate::processor::types::RpcHandler;
-    use crate::types::RpcId;
+    use crate::objects::{RpcCancellation, RpcSharedState};
+    use crate::processor::types::{RpcHandler, RpcTypedHandler};
+    use crate::types::{RpcId, RpcResponseSerialized, PARSE_ERROR_CODE};
 
     mock! {
         Handler {}
 
         #[async_trait]
         impl RpcHandler for Handler {
-            async fn call(&self, method: RpcMethod, params: Option<Value>) -> Result<Option<RpcResponseSerialized>, RpcError> {
+            async fn call(&self, ctx: RpcContext, method: RpcMethod, params: Option<Value>) -> Result<Option<RpcResponseSerialized>, RpcError> {
                 let output = FakeParam{
                     key: String::from("test-key"),
                     value: String::from("test-value")
@@ -127,11 +691,11 @@ mod tests {
                 RpcMethod::from(String::from(PING_RPC_METHOD)),
                 ping_controller,
             ))
-            .execute(request)
+            .execute(RpcContext::default(), request)
             .await;
 
         let jsonstr = serde_json::to_string(&response);
-        assert!(!jsonstr.is_err());
+        assert!(jsonstr.is_ok());
         assert_eq!(
             r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":1}"#,
             jsonstr.unwrap()
@@ -144,7 +708,7 @@ mod tests {
         mock_handler
             .expect_clone()
             .times(1)
-            .returning(|| MockHandler::new());
+            .returning(MockHandler::new);
 
         let ping_controller = Box::new(AgentPingHandler);
         let mock_controller = Box::new(mock_handler);
@@ -174,16 +738,1072 @@ mod tests {
             params: Some(Value::Null),
         };
 
-        let response = processor.execute(request).await;
+        let response = processor.execute(RpcContext::default(), request).await;
 
         let jsonstr = serde_json::to_string(&response);
-        assert!(!jsonstr.is_err());
+        assert!(jsonstr.is_ok());
         assert_eq!(
             r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":1}"#,
             jsonstr.unwrap()
         )
     }
 
+    #[tokio::test]
+    async fn test_processor_execute_batch() {
+        let processor = RpcProcessor::default();
+        let requests = vec![
+            json!({"jsonrpc": "2.0", "method": PING_RPC_METHOD, "id": 1}),
+            json!({"foo": "bar"}),
+            json!({"jsonrpc": "2.0", "method": "unknown", "id": "abc"}),
+        ];
+
+        let responses = processor
+            .execute_batch(RpcContext::default(), requests)
+            .await;
+        let jsonstr = serde_json::to_string(&responses);
+        assert!(jsonstr.is_ok());
+        assert_eq!(
+            r#"[{"jsonrpc":"2.0","result":{"message":"pong!"},"id":1},{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":null},{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":"abc"}]"#,
+            jsonstr.unwrap()
+        )
+    }
+
+    #[tokio::test]
+    async fn test_processor_execute_batch_sequential() {
+        let mut processor = RpcProcessor::default();
+        processor.set_batch_concurrency(0);
+        assert_eq!(processor.batch_concurrency(), 1);
+
+        let requests = (1..=5)
+            .map(|id| json!({"jsonrpc": "2.0", "method": PING_RPC_METHOD, "id": id}))
+            .collect();
+
+        let responses = processor
+            .execute_batch(RpcContext::default(), requests)
+            .await;
+        let ids: Vec<Option<RpcId>> = responses.into_iter().map(|resp| resp.id).collect();
+        assert_eq!(
+            ids,
+            (1..=5)
+                .map(|id| Some(RpcId::IntegerVal(id)))
+                .collect::<Vec<_>>()
+        )
+    }
+
+    #[tokio::test]
+    async fn test_processor_execute_payload() {
+        let processor = RpcProcessor::default();
+        let table = vec![
+            (
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1}"#,
+                r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":1}"#,
+            ),
+            (
+                r#"[{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1}]"#,
+                r#"[{"jsonrpc":"2.0","result":{"message":"pong!"},"id":1}]"#,
+            ),
+            (
+                r#"[]"#,
+                r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":null}"#,
+            ),
+            (
+                r#"[1]"#,
+                r#"[{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":null}]"#,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":-1}"#,
+                r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":-1}"#,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":null}"#,
+                r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":null}"#,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":"1"}"#,
+                r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":"1"}"#,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1.5}"#,
+                r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":null}"#,
+            ),
+        ];
+
+        for (input, expected) in table {
+            let payload: RpcPayload = serde_json::from_str(input).unwrap();
+            let response = processor
+                .execute_payload(RpcContext::default(), payload)
+                .await;
+            assert!(response.is_some());
+
+            let jsonstr = serde_json::to_string(&response);
+            assert!(jsonstr.is_ok());
+            assert_eq!(expected, jsonstr.unwrap())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_processor_execute_notification() {
+        let mut handler = MockHandler::new();
+
+        handler.expect_clone().returning(|| {
+            let mut copied = MockHandler::new();
+            copied
+                .expect_call()
+                .with(
+                    predicate::always(),
+                    predicate::eq(RpcMethod::from("test.notify")),
+                    predicate::eq(None),
+                )
+                .times(1)
+                .returning(|_, _, _| Ok(None));
+
+            copied
+        });
+
+        let mut processor = RpcProcessor::new();
+        processor.register_route(RpcRoute::new(
+            RpcMethod::from("test.notify"),
+            Box::new(handler),
+        ));
+
+        let request = RpcRequest {
+            id: None,
+            jsonrpc: String::from("2.0"),
+            method: String::from("test.notify"),
+            params: None,
+        };
+
+        let response = processor.execute(RpcContext::default(), request).await;
+        assert!(response.is_none())
+    }
+
+    #[tokio::test]
+    async fn test_processor_execute_payload_notifications() {
+        let processor = RpcProcessor::default();
+        let table = vec![
+            (r#"{"jsonrpc":"2.0","method":"prople.vessel.ping"}"#, None),
+            (r#"{"jsonrpc":"2.0","method":"unknown"}"#, None),
+            (
+                r#"[{"jsonrpc":"2.0","method":"prople.vessel.ping"},{"jsonrpc":"2.0","method":"unknown"}]"#,
+                None,
+            ),
+            (
+                r#"[{"jsonrpc":"2.0","method":"prople.vessel.ping"},{"jsonrpc":"2.0","method":"prople.vessel.ping","id":2}]"#,
+                Some(r#"[{"jsonrpc":"2.0","result":{"message":"pong!"},"id":2}]"#),
+            ),
+        ];
+
+        for (input, expected) in table {
+            let payload: RpcPayload = serde_json::from_str(input).unwrap();
+            let response = processor
+                .execute_payload(RpcContext::default(), payload)
+                .await;
+            let jsonstr = response.map(|resp| serde_json::to_string(&resp).unwrap());
+            assert_eq!(expected.map(String::from), jsonstr)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_processor_execute_bytes() {
+        let processor = RpcProcessor::default();
+        let table = vec![
+            (
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1}"#,
+                Some(r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":1}"#),
+            ),
+            (r#"{"jsonrpc":"2.0","method":"prople.vessel.ping"}"#, None),
+            (
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1"#,
+                Some(
+                    r#"{"jsonrpc":"2.0","error":{"code":-32700,"message":"Parse error"},"id":null}"#,
+                ),
+            ),
+            (
+                r#"[{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1},{"jsonrpc":"2.0","method""#,
+                Some(
+                    r#"{"jsonrpc":"2.0","error":{"code":-32700,"message":"Parse error"},"id":null}"#,
+                ),
+            ),
+            (
+                r#"{"jsonrpc":"1.0","method":"prople.vessel.ping","id":1}"#,
+                Some(
+                    r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":1}"#,
+                ),
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","params":"foo","id":"abc"}"#,
+                Some(
+                    r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":"abc"}"#,
+                ),
+            ),
+            (
+                r#"[{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1},{"jsonrpc":"2.0","method":1,"id":2}]"#,
+                Some(
+                    r#"[{"jsonrpc":"2.0","result":{"message":"pong!"},"id":1},{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":2}]"#,
+                ),
+            ),
+        ];
+
+        for (input, expected) in table {
+            let response = processor
+                .execute_bytes(RpcContext::default(), input.as_bytes())
+                .await;
+            let jsonstr = response.map(|resp| serde_json::to_string(&resp).unwrap());
+            assert_eq!(expected.map(String::from), jsonstr, "input: {}", input)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_processor_register_method_fn() {
+        let mut processor = RpcProcessor::new();
+        processor
+            .method("math.sum", |(a, b): (i64, i64)| async move {
+                Ok::<_, RpcError>(a + b)
+            })
+            .method("math.zero", || async { Ok::<_, RpcError>(0) });
+
+        let table = vec![
+            (
+                r#"{"jsonrpc":"2.0","method":"math.sum","params":[1,2],"id":1}"#,
+                r#"{"jsonrpc":"2.0","result":3,"id":1}"#,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"math.zero","id":2}"#,
+                r#"{"jsonrpc":"2.0","result":0,"id":2}"#,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"math.sum","params":{"a":1},"id":3}"#,
+                r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params","data":{"reason":"invalid type: map, expected a tuple of size 2"}},"id":3}"#,
+            ),
+        ];
+
+        for (input, expected) in table {
+            let response = processor
+                .execute_bytes(RpcContext::default(), input.as_bytes())
+                .await;
+            let jsonstr = serde_json::to_string(&response.unwrap()).unwrap();
+            assert_eq!(expected, jsonstr)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_processor_execute_with_context() {
+        let mut processor = RpcProcessor::new();
+        processor.method("test.ctx", |ctx: RpcContext, _: ()| async move {
+            Ok::<_, RpcError>(json!({
+                "id": ctx.id(),
+                "method": ctx.method().map(|method| method.to_string()),
+                "agent": ctx.header("x-agent"),
+            }))
+        });
+
+        let mut ctx = RpcContext::new();
+        ctx.insert_header("X-Agent", "vessel");
+
+        let request = r#"[{"jsonrpc":"2.0","method":"test.ctx","id":1},{"jsonrpc":"2.0","method":"test.ctx","id":"2"}]"#;
+        let response = processor.execute_bytes(ctx, request.as_bytes()).await;
+        let jsonstr = serde_json::to_string(&response.unwrap()).unwrap();
+        assert_eq!(
+            jsonstr,
+            r#"[{"jsonrpc":"2.0","result":{"agent":"vessel","id":1,"method":"test.ctx"},"id":1},{"jsonrpc":"2.0","result":{"agent":"vessel","id":"2","method":"test.ctx"},"id":"2"}]"#
+        )
+    }
+
+    #[tokio::test]
+    async fn test_processor_shared_state() {
+        struct FakeState {
+            prefix: String,
+        }
+
+        #[derive(Clone)]
+        struct FakeTypedHandler;
+
+        #[async_trait]
+        impl RpcTypedHandler for FakeTypedHandler {
+            type Params = (String,);
+            type Output = String;
+
+            async fn handle(
+                &self,
+                ctx: RpcContext,
+                _: RpcMethod,
+                (name,): (String,),
+            ) -> Result<String, RpcError> {
+                let state = ctx.state::<FakeState>().ok_or(RpcError::InternalError)?;
+                Ok(format!("{} {}", state.prefix, name))
+            }
+        }
+
+        let state = Arc::new(FakeState {
+            prefix: String::from("hello"),
+        });
+
+        let mut processor = RpcProcessor::new();
+        processor
+            .set_state(state)
+            .method(
+                "test.state",
+                |RpcSharedState(state): RpcSharedState<FakeState>, (name,): (String,)| async move {
+                    Ok::<_, RpcError>(format!("{} {}", state.prefix, name))
+                },
+            )
+            .method(
+                "test.missing",
+                |RpcSharedState(state): RpcSharedState<String>, _: ()| async move {
+                    Ok::<_, RpcError>(state.to_string())
+                },
+            )
+            .register_route(RpcRoute::new(
+                RpcMethod::from("test.typed"),
+                FakeTypedHandler.into_boxed(),
+            ));
+
+        let table = vec![
+            (
+                r#"{"jsonrpc":"2.0","method":"test.state","params":["world"],"id":1}"#,
+                r#"{"jsonrpc":"2.0","result":"hello world","id":1}"#,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"test.typed","params":["vessel"],"id":2}"#,
+                r#"{"jsonrpc":"2.0","result":"hello vessel","id":2}"#,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"test.missing","id":3}"#,
+                r#"{"jsonrpc":"2.0","error":{"code":-32603,"message":"Internal error","data":{"reason":"missing shared state"}},"id":3}"#,
+            ),
+        ];
+
+        for (input, expected) in table {
+            let response = processor
+                .execute_bytes(RpcContext::default(), input.as_bytes())
+                .await;
+            let jsonstr = serde_json::to_string(&response.unwrap()).unwrap();
+            assert_eq!(expected, jsonstr)
+        }
+    }
+
+    #[derive(Clone)]
+    struct FakeMiddleware {
+        name: &'static str,
+        reject: bool,
+        records: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl RpcMiddleware for FakeMiddleware {
+        async fn before(&self, ctx: &mut RpcContext, _: &RpcRequest) -> Result<(), RpcError> {
+            self.records
+                .lock()
+                .unwrap()
+                .push(format!("before:{}", self.name));
+
+            if self.reject {
+                return Err(RpcError::custom(1001).with_data(json!({"by": self.name})));
+            }
+
+            ctx.extensions_mut().insert(self.name);
+            Ok(())
+        }
+
+        async fn after(&self, _: &RpcContext, _: &RpcRequest, _: &mut RpcResponse<Value>) {
+            self.records
+                .lock()
+                .unwrap()
+                .push(format!("after:{}", self.name));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_processor_middlewares() {
+        let records = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let middleware = |name: &'static str, reject: bool| FakeMiddleware {
+            name,
+            reject,
+            records: records.clone(),
+        };
+
+        let mut processor = RpcProcessor::default();
+        processor
+            .middleware(middleware("global", false))
+            .register_route(
+                RpcRoute::new(
+                    RpcMethod::from("test.route"),
+                    Box::new(RpcFnHandler::new(|ctx: RpcContext, _: ()| async move {
+                        Ok::<_, RpcError>(ctx.extensions().get::<&'static str>().copied())
+                    })),
+                )
+                .with_middleware(middleware("route", false)),
+            )
+            .register_route(
+                RpcRoute::new(
+                    RpcMethod::from("test.rejected"),
+                    Box::new(RpcFnHandler::new(|_: ()| async move {
+                        Ok::<_, RpcError>("unreachable")
+                    })),
+                )
+                .with_middleware(middleware("guard", true))
+                .with_middleware(middleware("unreachable", false)),
+            );
+
+        let table = vec![
+            (
+                r#"{"jsonrpc":"2.0","method":"test.route","id":1}"#,
+                r#"{"jsonrpc":"2.0","result":"route","id":1}"#,
+                vec![
+                    "before:global",
+                    "before:route",
+                    "after:route",
+                    "after:global",
+                ],
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"test.rejected","id":2}"#,
+                r#"{"jsonrpc":"2.0","error":{"code":1001,"message":"Application error","data":{"by":"guard"}},"id":2}"#,
+                vec!["before:global", "before:guard", "after:global"],
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":3}"#,
+                r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":3}"#,
+                vec!["before:global", "after:global"],
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"test.unknown","id":4}"#,
+                r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":4}"#,
+                vec!["before:global", "after:global"],
+            ),
+        ];
+
+        for (input, expected, expected_records) in table {
+            records.lock().unwrap().clear();
+
+            let response = processor
+                .execute_bytes(RpcContext::default(), input.as_bytes())
+                .await;
+            let jsonstr = serde_json::to_string(&response.unwrap()).unwrap();
+            assert_eq!(expected, jsonstr);
+            assert_eq!(expected_records, *records.lock().unwrap())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_processor_middleware_override_response() {
+        #[derive(Clone)]
+        struct FakeMasking;
+
+        #[async_trait]
+        impl RpcMiddleware for FakeMasking {
+            async fn after(
+                &self,
+                _: &RpcContext,
+                _: &RpcRequest,
+                response: &mut RpcResponse<Value>,
+            ) {
+                if response.error.is_some() {
+                    response.error = Some(RpcErrorBuilder::build(RpcError::InternalError));
+                }
+            }
+        }
+
+        let mut processor = RpcProcessor::new();
+        processor
+            .middleware(FakeMasking)
+            .method("test.fail", |_: ()| async move {
+                Err::<(), _>(RpcError::HandlerError(String::from("secret")))
+            });
+
+        let response = processor
+            .execute_bytes(
+                RpcContext::default(),
+                br#"{"jsonrpc":"2.0","method":"test.fail","id":1}"#,
+            )
+            .await;
+        let jsonstr = serde_json::to_string(&response.unwrap()).unwrap();
+        assert_eq!(
+            r#"{"jsonrpc":"2.0","error":{"code":-32603,"message":"Internal error"},"id":1}"#,
+            jsonstr
+        )
+    }
+
+    #[tokio::test]
+    async fn test_processor_nest_and_merge() {
+        let records = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let mut users = RpcProcessor::new();
+        users
+            .middleware(FakeMiddleware {
+                name: "users",
+                reject: false,
+                records: records.clone(),
+            })
+            .method("get", |_: ()| async move { Ok::<_, RpcError>("user") });
+
+        let mut tools = RpcProcessor::new();
+        tools.method("tools.echo", |(msg,): (String,)| async move {
+            Ok::<_, RpcError>(msg)
+        });
+
+        let mut processor = RpcProcessor::default();
+        assert!(processor.nest("app.users", users).is_ok());
+        assert!(processor.merge(tools).is_ok());
+
+        let mut methods: Vec<String> = processor
+            .handlers()
+            .keys()
+            .map(|method| method.to_string())
+            .collect();
+        methods.sort();
+        assert_eq!(
+            methods,
+            vec!["app.users.get", "prople.vessel.ping", "tools.echo"]
+        );
+
+        let table = vec![
+            (
+                r#"{"jsonrpc":"2.0","method":"app.users.get","id":1}"#,
+                r#"{"jsonrpc":"2.0","result":"user","id":1}"#,
+                vec!["before:users", "after:users"],
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"tools.echo","params":["hello"],"id":2}"#,
+                r#"{"jsonrpc":"2.0","result":"hello","id":2}"#,
+                vec![],
+            ),
+        ];
+
+        for (input, expected, expected_records) in table {
+            records.lock().unwrap().clear();
+
+            let response = processor
+                .execute_bytes(RpcContext::default(), input.as_bytes())
+                .await;
+            let jsonstr = serde_json::to_string(&response.unwrap()).unwrap();
+            assert_eq!(expected, jsonstr);
+            assert_eq!(expected_records, *records.lock().unwrap())
+        }
+    }
+
+    #[test]
+    fn test_processor_compose_errors() {
+        let build = |methods: Vec<&'static str>| {
+            let mut processor = RpcProcessor::new();
+            for method in methods {
+                processor.method(method, |_: ()| async move { Ok::<_, RpcError>(()) });
+            }
+            processor
+        };
+
+        let mut processor = build(vec!["app.a", "app.b"]);
+
+        let duplicate = processor.nest("app", build(vec!["c", "b", "a"]));
+        assert!(duplicate.is_err());
+        assert_eq!(
+            duplicate.err().unwrap(),
+            RpcRouterError::DuplicateMethod(String::from("app.a"))
+        );
+        assert_eq!(processor.handlers().len(), 2);
+
+        let duplicate = processor.merge(build(vec!["app.b"]));
+        assert_eq!(
+            duplicate.err().unwrap(),
+            RpcRouterError::DuplicateMethod(String::from("app.b"))
+        );
+
+        for prefix in ["", ".app", "app."] {
+            let invalid = processor.nest(prefix, build(vec!["c"]));
+            assert_eq!(
+                invalid.err().unwrap(),
+                RpcRouterError::InvalidPrefix(String::from(prefix))
+            );
+        }
+
+        assert_eq!(processor.handlers().len(), 2)
+    }
+
+    #[tokio::test]
+    async fn test_processor_pattern_routes() {
+        let route = |name: &'static str| {
+            RpcRoute::new(
+                RpcMethod::from(name),
+                Box::new(RpcFnHandler::new(
+                    move |ctx: RpcContext, _: ()| async move {
+                        Ok::<_, RpcError>(format!(
+                            "{} <- {}",
+                            name,
+                            ctx.method().unwrap().to_string()
+                        ))
+                    },
+                )),
+            )
+        };
+
+        let mut processor = RpcProcessor::new();
+        processor
+            .register_route(route("app.*"))
+            .register_route(route("app.users.*"))
+            .register_route(route("app.users.get"));
+
+        let table = vec![
+            ("app.users.get", r#""app.users.get <- app.users.get""#),
+            ("app.users.list", r#""app.users.* <- app.users.list""#),
+            (
+                "app.users.roles.list",
+                r#""app.users.* <- app.users.roles.list""#,
+            ),
+            ("app.tools", r#""app.* <- app.tools""#),
+            ("app", r#"null"#),
+            ("other.users.get", r#"null"#),
+        ];
+
+        for (method, expected) in table {
+            let request = RpcRequest {
+                jsonrpc: String::from("2.0"),
+                method: String::from(method),
+                params: None,
+                id: Some(RpcId::IntegerVal(1)),
+            };
+
+            let response = processor.execute(RpcContext::default(), request).await;
+            let value = serde_json::to_value(response.unwrap()).unwrap();
+            assert_eq!(expected, value["result"].to_string());
+
+            if expected == "null" {
+                assert_eq!(value["error"]["code"], json!(-32601))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_processor_fallback() {
+        let mut processor = RpcProcessor::default();
+        processor.fallback(|ctx: RpcContext, params: Value| async move {
+            Ok::<_, RpcError>(json!({
+                "method": ctx.method().map(|method| method.to_string()),
+                "params": params,
+            }))
+        });
+
+        let table = vec![
+            (
+                r#"{"jsonrpc":"2.0","method":"prople.vessel.ping","id":1}"#,
+                r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":1}"#,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"plugin.call","params":[1,2],"id":2}"#,
+                r#"{"jsonrpc":"2.0","result":{"method":"plugin.call","params":[1,2]},"id":2}"#,
+            ),
+        ];
+
+        for (input, expected) in table {
+            let response = processor
+                .execute_bytes(RpcContext::default(), input.as_bytes())
+                .await;
+            let jsonstr = serde_json::to_string(&response.unwrap()).unwrap();
+            assert_eq!(expected, jsonstr)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_processor_error_catalog() {
+        let mut catalog = RpcErrorCatalog::new();
+        catalog.register(2001, "Insufficient balance").unwrap();
+
+        let mut processor = RpcProcessor::new();
+        processor
+            .set_error_catalog(catalog)
+            .method("wallet.send", || async {
+                Err::<(), _>(RpcError::custom(2001).with_data(json!({"balance": 10})))
+            })
+            .method("wallet.lock", || async {
+                Err::<(), _>(RpcError::custom(2002))
+            })
+            .method("wallet.parse", || async {
+                Err::<(), _>(RpcError::custom(PARSE_ERROR_CODE))
+            });
+
+        assert!(processor
+            .error_catalog()
+            .entries()
+            .iter()
+            .any(|entry| entry.code == 2001));
+        assert!(RpcProcessor::new().error_catalog().message(2001).is_none());
+
+        let table = vec![
+            (
+                r#"{"jsonrpc":"2.0","method":"wallet.send","id":1}"#,
+                r#"{"jsonrpc":"2.0","error":{"code":2001,"message":"Insufficient balance","data":{"balance":10}},"id":1}"#,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"wallet.lock","id":2}"#,
+                r#"{"jsonrpc":"2.0","error":{"code":2002,"message":"Application error"},"id":2}"#,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","method":"wallet.parse","id":3}"#,
+                r#"{"jsonrpc":"2.0","error":{"code":-32603,"message":"Internal error"},"id":3}"#,
+            ),
+        ];
+
+        for (input, expected) in table {
+            let response = processor
+                .execute_bytes(RpcContext::default(), input.as_bytes())
+                .await;
+            let jsonstr = serde_json::to_string(&response.unwrap()).unwrap();
+            assert_eq!(expected, jsonstr)
+        }
+    }
+
+    #[derive(Clone)]
+    struct SlowMiddleware {
+        cancelled: Arc<std::sync::Mutex<Option<RpcCancellation>>>,
+    }
+
+    #[async_trait]
+    impl RpcMiddleware for SlowMiddleware {
+        async fn before(&self, ctx: &mut RpcContext, _: &RpcRequest) -> Result<(), RpcError> {
+            *self.cancelled.lock().unwrap() = Some(ctx.cancellation().clone());
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_processor_timeouts() {
+        let cancelled = Arc::new(std::sync::Mutex::new(None));
+        let slow = |cancelled: Arc<std::sync::Mutex<Option<RpcCancellation>>>| {
+            move |ctx: RpcContext, _: ()| {
+                let cancelled = cancelled.clone();
+                async move {
+                    *cancelled.lock().unwrap() = Some(ctx.cancellation().clone());
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    Ok::<_, RpcError>("done")
+                }
+            }
+        };
+
+        let mut users = RpcProcessor::new();
+        users
+            .set_timeout(Duration::from_millis(10))
+            .method("slow", slow(cancelled.clone()));
+
+        let mut processor = RpcProcessor::new();
+        processor
+            .set_timeout(Duration::from_millis(10))
+            .method("test.slow", slow(cancelled.clone()))
+            .register_route(
+                RpcRoute::new(
+                    RpcMethod::from("test.patient"),
+                    Box::new(RpcFnHandler::new(slow(cancelled.clone()))),
+                )
+                .with_timeout(Duration::from_secs(5)),
+            )
+            .register_route(
+                RpcRoute::new(
+                    RpcMethod::from("test.guarded"),
+                    Box::new(RpcFnHandler::new(|_: ()| async move {
+                        Ok::<_, RpcError>("unreachable")
+                    })),
+                )
+                .with_middleware(SlowMiddleware {
+                    cancelled: cancelled.clone(),
+                }),
+            );
+        assert!(processor.nest("users", users).is_ok());
+
+        let table = vec![
+            (
+                "test.slow",
+                r#"{"jsonrpc":"2.0","error":{"code":-32001,"message":"Request timeout"},"id":1}"#,
+                true,
+            ),
+            (
+                "test.patient",
+                r#"{"jsonrpc":"2.0","result":"done","id":1}"#,
+                false,
+            ),
+            (
+                "users.slow",
+                r#"{"jsonrpc":"2.0","error":{"code":-32001,"message":"Request timeout"},"id":1}"#,
+                true,
+            ),
+            (
+                "test.guarded",
+                r#"{"jsonrpc":"2.0","error":{"code":-32001,"message":"Request timeout"},"id":1}"#,
+                true,
+            ),
+        ];
+
+        for (method, expected, expected_cancelled) in table {
+            let request = RpcRequest {
+                jsonrpc: String::from("2.0"),
+                method: String::from(method),
+                params: None,
+                id: Some(RpcId::IntegerVal(1)),
+            };
+
+            let ctx = RpcContext::default();
+            let response = processor.execute(ctx.clone(), request).await;
+            let jsonstr = serde_json::to_string(&response.unwrap()).unwrap();
+            assert_eq!(expected, jsonstr);
+
+            let token = cancelled.lock().unwrap().take().unwrap();
+            assert_eq!(token.is_cancelled(), expected_cancelled);
+            assert!(!ctx.cancellation().is_cancelled())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_processor_concurrency_limits() {
+        let (sender, _) = tokio::sync::broadcast::channel::<()>(1);
+        let blocking = |sender: tokio::sync::broadcast::Sender<()>| {
+            move |_: ()| {
+                let mut receiver = sender.subscribe();
+                async move {
+                    let _ = receiver.recv().await;
+                    Ok::<_, RpcError>("done")
+                }
+            }
+        };
+
+        let global = RpcConcurrencyLimit::new(2);
+        let mut processor = RpcProcessor::new();
+        processor
+            .set_concurrency_limit(global.clone())
+            .method(
+                "test.fast",
+                |_: ()| async move { Ok::<_, RpcError>("fast") },
+            )
+            .register_route(
+                RpcRoute::new(
+                    RpcMethod::from("test.blocking"),
+                    Box::new(RpcFnHandler::new(blocking(sender.clone()))),
+                )
+                .with_concurrency_limit(
+                    RpcConcurrencyLimit::new(1).with_retry_after(Duration::from_secs(3)),
+                ),
+            );
+
+        let processor = Arc::new(processor);
+        let execute = |method: &'static str| {
+            let processor = processor.clone();
+            async move {
+                let request = RpcRequest {
+                    jsonrpc: String::from("2.0"),
+                    method: String::from(method),
+                    params: None,
+                    id: Some(RpcId::IntegerVal(1)),
+                };
+
+                let response = processor.execute(RpcContext::default(), request).await;
+                serde_json::to_string(&response.unwrap()).unwrap()
+            }
+        };
+
+        let running = tokio::spawn(execute("test.blocking"));
+        while global.available() == 2 {
+            tokio::task::yield_now().await;
+        }
+
+        assert_eq!(
+            execute("test.blocking").await,
+            r#"{"jsonrpc":"2.0","error":{"code":-32002,"message":"Server busy","data":{"retry_after":3}},"id":1}"#
+        );
+        assert_eq!(
+            execute("test.fast").await,
+            r#"{"jsonrpc":"2.0","result":"fast","id":1}"#
+        );
+
+        let _ = sender.send(());
+        assert_eq!(
+            running.await.unwrap(),
+            r#"{"jsonrpc":"2.0","result":"done","id":1}"#
+        );
+        assert_eq!(global.available(), 2)
+    }
+
+    #[tokio::test]
+    async fn test_processor_concurrency_queue() {
+        let (sender, receiver) = tokio::sync::watch::channel(false);
+        let global = RpcConcurrencyLimit::new(2);
+        let route = RpcConcurrencyLimit::new(1).with_queue(4);
+
+        let mut processor = RpcProcessor::new();
+        processor
+            .set_concurrency_limit(global.clone())
+            .method(
+                "test.fast",
+                |_: ()| async move { Ok::<_, RpcError>("fast") },
+            )
+            .register_route(
+                RpcRoute::new(
+                    RpcMethod::from("test.blocking"),
+                    Box::new(RpcFnHandler::new(move |_: ()| {
+                        let mut receiver = receiver.clone();
+                        async move {
+                            let _ = receiver.wait_for(|released| *released).await;
+                            Ok::<_, RpcError>("done")
+                        }
+                    })),
+                )
+                .with_concurrency_limit(route.clone()),
+            );
+
+        let processor = Arc::new(processor);
+        let execute = |method: &'static str| {
+            let processor = processor.clone();
+            async move {
+                let request = RpcRequest {
+                    jsonrpc: String::from("2.0"),
+                    method: String::from(method),
+                    params: None,
+                    id: Some(RpcId::IntegerVal(1)),
+                };
+
+                let response = processor.execute(RpcContext::default(), request).await;
+                serde_json::to_string(&response.unwrap()).unwrap()
+            }
+        };
+
+        let tasks: Vec<_> = (0..4)
+            .map(|_| tokio::spawn(execute("test.blocking")))
+            .collect();
+        for _ in 0..100 {
+            if route.queued() == 3 {
+                break;
+            }
+
+            tokio::task::yield_now().await;
+        }
+
+        assert_eq!(route.queued(), 3);
+        assert_eq!(global.available(), 1);
+        assert_eq!(
+            execute("test.fast").await,
+            r#"{"jsonrpc":"2.0","result":"fast","id":1}"#
+        );
+        assert_eq!(
+            execute("test.fast").await,
+            r#"{"jsonrpc":"2.0","result":"fast","id":1}"#
+        );
+
+        let _ = sender.send(true);
+        for task in tasks {
+            assert_eq!(
+                task.await.unwrap(),
+                r#"{"jsonrpc":"2.0","result":"done","id":1}"#
+            );
+        }
+        assert_eq!(global.available(), 2)
+    }
+
+    #[tokio::test]
+    async fn test_processor_scopes() {
+        let admin = |method: &str, scopes: &[&str]| {
+            RpcRoute::new(
+                RpcMethod::from(method),
+                Box::new(RpcFnHandler::new(|_: ()| async move {
+                    Ok::<_, RpcError>("done")
+                })),
+            )
+            .with_scopes(scopes)
+        };
+
+        let mut nested = RpcProcessor::new();
+        nested.register_route(admin("restart", &["admin"]));
+
+        let mut processor = RpcProcessor::new();
+        processor
+            .method(
+                "test.public",
+                |_: ()| async move { Ok::<_, RpcError>("done") },
+            )
+            .register_route(admin("test.write", &["read", "write"]))
+            .fallback(|_: ()| async move { Ok::<_, RpcError>("fallback") })
+            .set_fallback_scopes(&["plugins"])
+            .nest("test.admin", nested)
+            .unwrap();
+
+        assert_eq!(
+            processor.scopes(),
+            BTreeMap::from([
+                (String::from(FALLBACK_ROUTE), vec![String::from("plugins")]),
+                (
+                    String::from("test.admin.restart"),
+                    vec![String::from("admin")]
+                ),
+                (String::from("test.public"), vec![]),
+                (
+                    String::from("test.write"),
+                    vec![String::from("read"), String::from("write")]
+                ),
+            ])
+        );
+
+        let reader = RpcPrincipal::new("reader").with_scopes(&["read"]);
+        assert_eq!(processor.allowed_methods(&reader), vec!["test.public"]);
+
+        let plugin = RpcPrincipal::new("plugin").with_scopes(&["plugins"]);
+        assert_eq!(processor.allowed_methods(&plugin), vec!["*", "test.public"]);
+
+        let table = vec![
+            (
+                None,
+                "test.public",
+                r#"{"jsonrpc":"2.0","result":"done","id":1}"#,
+            ),
+            (
+                None,
+                "test.write",
+                r#"{"jsonrpc":"2.0","error":{"code":-32004,"message":"Unauthorized"},"id":1}"#,
+            ),
+            (
+                Some(reader.clone()),
+                "test.write",
+                r#"{"jsonrpc":"2.0","error":{"code":-32005,"message":"Forbidden","data":{"missing":["write"],"required":["read","write"]}},"id":1}"#,
+            ),
+            (
+                Some(reader.clone().with_scopes(&["write"])),
+                "test.write",
+                r#"{"jsonrpc":"2.0","result":"done","id":1}"#,
+            ),
+            (
+                Some(RpcPrincipal::new("admin").with_scopes(&["admin"])),
+                "test.admin.restart",
+                r#"{"jsonrpc":"2.0","result":"done","id":1}"#,
+            ),
+            (
+                None,
+                "plugin.call",
+                r#"{"jsonrpc":"2.0","error":{"code":-32004,"message":"Unauthorized"},"id":1}"#,
+            ),
+            (
+                Some(reader),
+                "plugin.call",
+                r#"{"jsonrpc":"2.0","error":{"code":-32005,"message":"Forbidden","data":{"missing":["plugins"],"required":["plugins"]}},"id":1}"#,
+            ),
+            (
+                Some(plugin),
+                "plugin.call",
+                r#"{"jsonrpc":"2.0","result":"fallback","id":1}"#,
+            ),
+        ];
+
+        for (principal, method, expected) in table {
+            let mut ctx = RpcContext::default();
+            if let Some(principal) = principal {
+                ctx.set_principal(principal);
+            }
+
+            let request = RpcRequest {
+                jsonrpc: String::from("2.0"),
+                method: String::from(method),
+                params: None,
+                id: Some(RpcId::IntegerVal(1)),
+            };
+
+            let response = processor.execute(ctx, request).await;
+            assert_eq!(serde_json::to_string(&response.unwrap()).unwrap(), expected)
+        }
+    }
+
     #[tokio::test]
     async fn test_processor_handler_error() {
         let mut handler = MockHandler::new();
@@ -193,11 +1813,12 @@ mod tests {
             copied
                 .expect_call()
                 .with(
+                    predicate::always(),
                     predicate::eq(RpcMethod::from("test.mock")),
                     predicate::eq(Some(Value::Null)),
                 )
                 .times(1)
-                .returning(|_, _| Err(RpcError::InvalidParams));
+                .returning(|_, _, _| Err(RpcError::InvalidParams));
 
             copied
         });
@@ -215,9 +1836,9 @@ mod tests {
             params: Some(Value::Null),
         };
 
-        let response = processor.execute(request).await;
+        let response = processor.execute(RpcContext::default(), request).await;
         let jsonstr = serde_json::to_string(&response);
-        assert!(!jsonstr.is_err());
+        assert!(jsonstr.is_ok());
         assert_eq!(
             r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params"},"id":1}"#,
             jsonstr.unwrap()
@@ -0,0 +1,124 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use rst_common::standard::futures::future::BoxFuture;
+use rst_common::standard::serde_json::Value;
+use rst_common::with_http_tokio::tower::Service;
+
+use crate::objects::{RpcContext, RpcProcessor, RpcRegistry, RpcRequest, RpcResponse};
+
+fn serve(
+    processor: Arc<RpcProcessor>,
+    request: RpcRequest,
+) -> BoxFuture<'static, Result<Option<RpcResponse<Value>>, Infallible>> {
+    Box::pin(async move { Ok(processor.execute(RpcContext::default(), request).await) })
+}
+
+/// A shared [`RpcProcessor`] can be used as a `tower::Service<RpcRequest>`, which makes it
+/// usable with any `tower` layers, like timeout, concurrency limit or buffer. Each call will
+/// receive an empty [`RpcContext`] and a notification will be answered with `None`
+impl Service<RpcRequest> for Arc<RpcProcessor> {
+    type Response = Option<RpcResponse<Value>>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RpcRequest) -> Self::Future {
+        serve(self.clone(), request)
+    }
+}
+
+/// Each call of the [`RpcRegistry`] service will use its latest processor
+impl Service<RpcRequest> for RpcRegistry {
+    type Response = Option<RpcResponse<Value>>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RpcRequest) -> Self::Future {
+        serve(self.load(), request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use rst_common::standard::serde_json;
+    use rst_common::with_tokio::tokio;
+
+    use crate::types::{RpcError, RpcId};
+    use tower::{ServiceBuilder, ServiceExt};
+
+    fn build_request(method: &str, id: Option<RpcId>) -> RpcRequest {
+        RpcRequest {
+            jsonrpc: String::from("2.0"),
+            method: String::from(method),
+            params: None,
+            id,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_service_call() {
+        let processor = Arc::new(RpcProcessor::default());
+        let registry = RpcRegistry::new(RpcProcessor::default());
+
+        let response = processor
+            .clone()
+            .oneshot(build_request(
+                "prople.vessel.ping",
+                Some(RpcId::IntegerVal(1)),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&response.unwrap()).unwrap(),
+            r#"{"jsonrpc":"2.0","result":{"message":"pong!"},"id":1}"#
+        );
+
+        let notification = processor
+            .oneshot(build_request("prople.vessel.ping", None))
+            .await
+            .unwrap();
+        assert!(notification.is_none());
+
+        registry.method("test.hello", || async { Ok::<_, RpcError>("hello") });
+        let response = registry
+            .oneshot(build_request("test.hello", Some(RpcId::IntegerVal(2))))
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&response.unwrap()).unwrap(),
+            r#"{"jsonrpc":"2.0","result":"hello","id":2}"#
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_service_with_layers() {
+        let mut processor = RpcProcessor::new();
+        processor.method("test.slow", |_: ()| async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok::<_, RpcError>("done")
+        });
+
+        let svc = ServiceBuilder::new()
+            .timeout(Duration::from_millis(20))
+            .concurrency_limit(4)
+            .service(Arc::new(processor));
+
+        let response = svc
+            .oneshot(build_request("test.slow", Some(RpcId::IntegerVal(1))))
+            .await;
+        assert!(response.is_err())
+    }
+}
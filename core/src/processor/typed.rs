@@ -0,0 +1,165 @@
+use rst_common::standard::async_trait::async_trait;
+use rst_common::standard::serde::de::DeserializeOwned;
+use rst_common::standard::serde::Serialize;
+use rst_common::standard::serde_json::{self, json, Value};
+
+use crate::objects::RpcContext;
+use crate::types::RpcError;
+
+use super::base::{Handler, HandlerBoxed, HandlerOutput, Method};
+
+/// `parse_params` used to deserialize the request params into given type
+///
+/// The params may be a positional array or a named object, both of them will be
+/// deserialized using `serde`. An omitted params will be deserialized from `null`,
+/// so it's possible to use an [`Option`] or unit type for a method without any params.
+///
+/// If the params cannot be deserialized, it will return [`RpcError::InvalidParams`]
+/// with the explanation put into the error `data`
+pub fn parse_params<P>(params: Option<Value>) -> Result<P, RpcError>
+where
+    P: DeserializeOwned,
+{
+    serde_json::from_value(params.unwrap_or(Value::Null))
+        .map_err(|err| RpcError::InvalidParams.with_data(json!({"reason": err.to_string()})))
+}
+
+/// `TypedHandler` is a typed layer of the [`Handler`]
+///
+/// Instead of working with the raw params and the erased response, the implementers
+/// only need to define their params and output types. The params will be parsed
+/// using [`parse_params`] before the handler being called
+#[async_trait]
+pub trait TypedHandler: Clone + Send + Sync + 'static {
+    type Params: DeserializeOwned + Send;
+    type Output: Serialize + Send + Sync + 'static;
+
+    async fn handle(
+        &self,
+        ctx: RpcContext,
+        method: Method,
+        params: Self::Params,
+    ) -> Result<Self::Output, RpcError>;
+
+    /// `into_boxed` used to adapt current handler into [`HandlerBoxed`], so it can
+    /// be registered through the [`super::base::Route`]
+    fn into_boxed(self) -> HandlerBoxed
+    where
+        Self: Sized,
+    {
+        Box::new(TypedAdapter::new(self))
+    }
+}
+
+/// `TypedAdapter` is an object used to adapt the [`TypedHandler`] into the [`Handler`]
+#[derive(Clone)]
+pub struct TypedAdapter<H>(H);
+
+impl<H> TypedAdapter<H>
+where
+    H: TypedHandler,
+{
+    pub fn new(handler: H) -> Self {
+        Self(handler)
+    }
+}
+
+#[async_trait]
+impl<H> Handler for TypedAdapter<H>
+where
+    H: TypedHandler,
+{
+    async fn call(&self, ctx: RpcContext, method: Method, params: Option<Value>) -> HandlerOutput {
+        let params = parse_params::<H::Params>(params)?;
+        let output = self.0.handle(ctx, method, params).await?;
+        Ok(Some(Box::new(output)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rst_common::standard::serde::{self, Deserialize};
+    use rst_common::with_tokio::tokio;
+
+    #[derive(Deserialize)]
+    #[serde(crate = "self::serde")]
+    struct FakeParams {
+        a: i64,
+        b: i64,
+    }
+
+    #[derive(Serialize)]
+    #[serde(crate = "self::serde")]
+    struct FakeOutput {
+        total: i64,
+    }
+
+    #[derive(Clone)]
+    struct FakeSumHandler;
+
+    #[async_trait]
+    impl TypedHandler for FakeSumHandler {
+        type Params = FakeParams;
+        type Output = FakeOutput;
+
+        async fn handle(
+            &self,
+            _: RpcContext,
+            _: Method,
+            params: FakeParams,
+        ) -> Result<FakeOutput, RpcError> {
+            Ok(FakeOutput {
+                total: params.a + params.b,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_typed_handler_params() {
+        let handler = FakeSumHandler.into_boxed();
+        let table = vec![json!([1, 2]), json!({"a": 1, "b": 2})];
+
+        for params in table {
+            let output = handler
+                .call(
+                    RpcContext::default(),
+                    Method::from("math.sum"),
+                    Some(params),
+                )
+                .await
+                .unwrap()
+                .unwrap();
+
+            let jsonstr = serde_json::to_string(output.as_ref()).unwrap();
+            assert_eq!(jsonstr, r#"{"total":3}"#)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_typed_handler_invalid_params() {
+        let handler = FakeSumHandler.into_boxed();
+        let table = vec![None, Some(json!([1])), Some(json!({"a": "1", "b": 2}))];
+
+        for params in table {
+            let result = handler
+                .call(RpcContext::default(), Method::from("math.sum"), params)
+                .await;
+            assert!(result.is_err());
+
+            let err = result.err().unwrap();
+            assert_eq!(err.build().0, RpcError::InvalidParams.build().0);
+            assert!(err.data().unwrap().get("reason").is_some())
+        }
+    }
+
+    #[test]
+    fn test_parse_params_optional() {
+        let params: Option<FakeParams> = parse_params(None).unwrap();
+        assert!(params.is_none());
+
+        let params: () = parse_params(None).unwrap();
+        assert_eq!(params, ());
+    }
+}
@@ -1,7 +1,11 @@
-use rst_common::standard::serde::{self, Deserialize, Serialize};
-use rst_common::standard::serde_json::Value;
+use rst_common::standard::serde::{self, Deserialize, Deserializer, Serialize};
+use rst_common::standard::serde_json::{self, Value};
 
-use crate::types::RpcId;
+use crate::objects::{RpcErrorBuilder, RpcResponse};
+use crate::types::{RpcError, RpcId};
+
+/// `JSONRPC_VERSION` is the only protocol version supported
+pub const JSONRPC_VERSION: &str = "2.0";
 
 /// `RpcRequestObject` used to modeling `JSON-RPC` request spc model
 ///
@@ -13,10 +17,108 @@ pub struct RpcRequest {
     pub method: String,
     pub params: Option<Value>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// An absent `id` means the request is a notification, while an explicit `null`
+    /// will be parsed as [`RpcId::Null`]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_id"
+    )]
     pub id: Option<RpcId>,
 }
 
+fn deserialize_id<'de, D>(deserializer: D) -> Result<Option<RpcId>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    RpcId::deserialize(deserializer).map(Some)
+}
+
+impl RpcRequest {
+    /// `is_notification` used to check if current request is a notification
+    ///
+    /// Based on the specification, a notification is a request object without an `id` member.
+    /// The server must not reply to a notification
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+
+    /// `validate` used to check the request envelope based on the specification
+    ///
+    /// The `jsonrpc` member must be exactly `2.0` and the `params` member, if present,
+    /// must be a structured value, an array or an object. An explicit `null` params will
+    /// be treated as an omitted params
+    pub fn validate(&self) -> Result<(), RpcError> {
+        if self.jsonrpc != JSONRPC_VERSION {
+            return Err(RpcError::InvalidRequest);
+        }
+
+        match self.params {
+            None | Some(Value::Null) | Some(Value::Array(_)) | Some(Value::Object(_)) => Ok(()),
+            _ => Err(RpcError::InvalidRequest),
+        }
+    }
+
+    /// `from_value` used to parse and validate a raw request object
+    ///
+    /// If the given value is not a valid request object, it will try to recover
+    /// the request `id` so the error response still can be correlated by the client
+    pub fn from_value(value: Value) -> Result<Self, RpcRequestError> {
+        let id = value.get("id").and_then(|id| RpcId::deserialize(id).ok());
+
+        let request = serde_json::from_value::<RpcRequest>(value).map_err(|_| RpcRequestError {
+            error: RpcError::InvalidRequest,
+            id: id.clone(),
+        })?;
+
+        request
+            .validate()
+            .map_err(|error| RpcRequestError { error, id })?;
+
+        Ok(request)
+    }
+}
+
+/// `RpcRequestError` is an error happened when parsing or validating a request object,
+/// including for its recovered request `id`
+#[derive(Debug, Clone)]
+pub struct RpcRequestError {
+    pub error: RpcError,
+    pub id: Option<RpcId>,
+}
+
+impl RpcRequestError {
+    /// `into_response` used to build the error response object of current error
+    pub fn into_response<T>(self) -> RpcResponse<T> {
+        RpcResponse::with_error(Some(RpcErrorBuilder::build(self.error)), self.id)
+    }
+}
+
+/// `RpcPayload` used to modeling the incoming request body, which can be a single
+/// request object or a batch (an array) of request objects
+///
+/// The request objects are kept as raw [`Value`] so a malformed request object will not
+/// be rejected by the deserializer, it will be answered with its own error response instead
+///
+/// Ref: <https://www.jsonrpc.org/specification#batch>
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "self::serde", untagged)]
+pub enum RpcPayload {
+    Batch(Vec<Value>),
+    Single(Value),
+}
+
+impl RpcPayload {
+    /// `from_slice` used to parse raw request body into [`RpcPayload`]
+    ///
+    /// It will return [`RpcError::ParseError`] if given bytes is not a valid JSON.
+    /// The request objects will not be validated here, since each of them must be
+    /// answered independently
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, RpcError> {
+        serde_json::from_slice(bytes).map_err(|_| RpcError::ParseError)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -59,7 +161,7 @@ mod tests {
         let jsonstr = r#"{"jsonrpc":"2.0","method":"testing","params":[1,2], "id": 1}"#;
         let jsonobj: Result<RpcRequest, Error> = serde_json::from_str(jsonstr);
 
-        assert!(!jsonobj.is_err());
+        assert!(jsonobj.is_ok());
 
         let jsonreq = jsonobj.unwrap();
         assert_eq!(jsonreq.jsonrpc.as_str(), "2.0");
@@ -67,7 +169,7 @@ mod tests {
 
         let params_unwrapped = jsonreq.params.unwrap();
         let params = params_unwrapped.as_array().unwrap();
-      
+
         assert_eq!(params.len(), 2);
         assert_eq!(params[0].as_u64().unwrap(), 1);
         assert_eq!(params[1].as_u64().unwrap(), 2)
@@ -78,7 +180,7 @@ mod tests {
         let jsonstr = r#"{"jsonrpc":"2.0","method":"testing","params": {"key": "testkey", "value": "testvalue"}, "id": 1}"#;
         let jsonobj: Result<RpcRequest, Error> = serde_json::from_str(jsonstr);
 
-        assert!(!jsonobj.is_err());
+        assert!(jsonobj.is_ok());
         let payload = jsonobj.unwrap();
 
         let params_unwrapped = payload.params.unwrap();
@@ -86,13 +188,174 @@ mod tests {
         assert_eq!("testvalue", params_unwrapped.get("value").unwrap())
     }
 
+    #[test]
+    fn test_deserialize_payload_single() {
+        let jsonstr = r#"{"jsonrpc":"2.0","method":"testing","params":[1,2],"id":1}"#;
+        let payload: Result<RpcPayload, Error> = serde_json::from_str(jsonstr);
+        assert!(payload.is_ok());
+
+        match payload.unwrap() {
+            RpcPayload::Single(req) => assert_eq!(req.get("method").unwrap(), "testing"),
+            RpcPayload::Batch(_) => panic!("expected single payload"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_payload_batch() {
+        let jsonstr = r#"[{"jsonrpc":"2.0","method":"testing","id":1}, 1, {"foo": "bar"}]"#;
+        let payload: Result<RpcPayload, Error> = serde_json::from_str(jsonstr);
+        assert!(payload.is_ok());
+
+        match payload.unwrap() {
+            RpcPayload::Batch(values) => assert_eq!(values.len(), 3),
+            RpcPayload::Single(_) => panic!("expected batch payload"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_null_id() {
+        let jsonstr = r#"{"jsonrpc":"2.0","method":"testing","id":null}"#;
+        let jsonreq: RpcRequest = serde_json::from_str(jsonstr).unwrap();
+
+        assert_eq!(jsonreq.id, Some(RpcId::Null));
+        assert!(!jsonreq.is_notification());
+
+        let jsonstr = serde_json::to_string(&jsonreq).unwrap();
+        assert_eq!(
+            jsonstr,
+            r#"{"jsonrpc":"2.0","method":"testing","params":null,"id":null}"#
+        )
+    }
+
+    #[test]
+    fn test_deserialize_with_negative_id() {
+        let jsonstr = r#"{"jsonrpc":"2.0","method":"testing","id":-1}"#;
+        let jsonreq: RpcRequest = serde_json::from_str(jsonstr).unwrap();
+        assert_eq!(jsonreq.id, Some(RpcId::SignedVal(-1)))
+    }
+
+    #[test]
+    fn test_deserialize_with_fractional_id() {
+        let jsonstr = r#"{"jsonrpc":"2.0","method":"testing","id":1.5}"#;
+        let jsonobj: Result<RpcRequest, Error> = serde_json::from_str(jsonstr);
+        assert!(jsonobj.is_err())
+    }
+
+    #[test]
+    fn test_validate_request() {
+        let table = vec![
+            (
+                json!({"jsonrpc": "2.0", "method": "testing", "id": 1}),
+                true,
+            ),
+            (
+                json!({"jsonrpc": "2.0", "method": "testing", "params": [1], "id": 1}),
+                true,
+            ),
+            (
+                json!({"jsonrpc": "2.0", "method": "testing", "params": {"a": 1}}),
+                true,
+            ),
+            (
+                json!({"jsonrpc": "2.0", "method": "testing", "params": null}),
+                true,
+            ),
+            (
+                json!({"jsonrpc": "1.0", "method": "testing", "id": 1}),
+                false,
+            ),
+            (
+                json!({"jsonrpc": "2.0", "method": "testing", "params": "str"}),
+                false,
+            ),
+            (
+                json!({"jsonrpc": "2.0", "method": "testing", "params": 1}),
+                false,
+            ),
+            (
+                json!({"jsonrpc": "2.0", "method": "testing", "params": true}),
+                false,
+            ),
+        ];
+
+        for (input, expected) in table {
+            let request: RpcRequest = serde_json::from_value(input.clone()).unwrap();
+            assert_eq!(request.validate().is_ok(), expected, "input: {}", input)
+        }
+    }
+
+    #[test]
+    fn test_request_from_value_recover_id() {
+        let table = vec![
+            (
+                json!({"jsonrpc": "1.0", "method": "testing", "id": 1}),
+                Some(RpcId::IntegerVal(1)),
+            ),
+            (
+                json!({"jsonrpc": "2.0", "method": 1, "id": "abc"}),
+                Some(RpcId::StringVal(String::from("abc"))),
+            ),
+            (
+                json!({"jsonrpc": "2.0", "method": "testing", "params": 1, "id": -1}),
+                Some(RpcId::SignedVal(-1)),
+            ),
+            (json!({"method": "testing", "id": null}), Some(RpcId::Null)),
+            (
+                json!({"jsonrpc": "2.0", "method": "testing", "id": 1.5}),
+                None,
+            ),
+            (json!("2.0"), None),
+            (json!(1), None),
+        ];
+
+        for (input, expected) in table {
+            let result = RpcRequest::from_value(input.clone());
+            assert!(result.is_err(), "input: {}", input);
+
+            let err = result.unwrap_err();
+            assert!(matches!(err.error, RpcError::InvalidRequest));
+            assert_eq!(err.id, expected, "input: {}", input)
+        }
+    }
+
+    #[test]
+    fn test_request_error_into_response() {
+        let err = RpcRequestError {
+            error: RpcError::InvalidRequest,
+            id: Some(RpcId::IntegerVal(1)),
+        };
+
+        let response: RpcResponse<Value> = err.into_response();
+        let jsonstr = serde_json::to_string(&response).unwrap();
+        assert_eq!(
+            jsonstr,
+            r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":1}"#
+        )
+    }
+
+    #[test]
+    fn test_payload_from_slice() {
+        let payload = RpcPayload::from_slice(br#"{"jsonrpc":"2.0","method":"testing"}"#);
+        assert!(matches!(payload, Ok(RpcPayload::Single(_))));
+
+        let payload = RpcPayload::from_slice(br#"[{"jsonrpc":"2.0","method":"testing"}]"#);
+        assert!(matches!(payload, Ok(RpcPayload::Batch(_))));
+
+        let payload = RpcPayload::from_slice(br#"{"jsonrpc":"2.0","method""#);
+        assert!(matches!(payload, Err(RpcError::ParseError)));
+
+        let payload = RpcPayload::from_slice(b"");
+        assert!(matches!(payload, Err(RpcError::ParseError)))
+    }
+
     #[test]
     fn test_deserialize_without_id() {
         let jsonstr = r#"{"jsonrpc":"2.0","method":"testing","params":[1,2]}"#;
         let jsonobj: Result<RpcRequest, Error> = serde_json::from_str(jsonstr);
-        assert!(!jsonobj.is_err());
+        assert!(jsonobj.is_ok());
 
         let jsonreq = jsonobj.unwrap();
-        assert!(jsonreq.id.is_none())
+        assert!(jsonreq.id.is_none());
+        assert!(jsonreq.is_notification())
     }
 }
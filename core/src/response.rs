@@ -44,6 +44,17 @@ impl<T> RpcResponse<T> {
     }
 }
 
+/// `RpcPayloadResponse` used to modeling the response body for the given [`crate::objects::RpcPayload`]
+///
+/// A single request will be answered with a single response object, while a batch
+/// request will be answered with an array of response objects
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(crate = "self::serde", untagged)]
+pub enum RpcPayloadResponse<T> {
+    Batch(Vec<RpcResponse<T>>),
+    Single(RpcResponse<T>),
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,7 +78,7 @@ mod tests {
         let response: RpcResponse<FakeParam> =
             RpcResponse::with_success(Some(result), None);
         let jsonstr = serde_json::to_string(&response);
-        assert!(!jsonstr.is_err());
+        assert!(jsonstr.is_ok());
         assert_eq!(
             jsonstr.unwrap(),
             r#"{"jsonrpc":"2.0","result":{"key":"testkey","value":"testvalue"},"id":null}"#
@@ -84,24 +95,45 @@ mod tests {
         let response: RpcResponse<FakeParam> =
             RpcResponse::with_success(Some(result.clone()), None);
         let jsonstr = serde_json::to_string(&response);
-        assert!(!jsonstr.is_err());
+        assert!(jsonstr.is_ok());
 
         let output: Result<RpcResponse<FakeParam>, serde_json::Error> =
             serde_json::from_str(jsonstr.unwrap().as_str());
 
-        assert!(!output.is_err());
+        assert!(output.is_ok());
 
         let output_obj = output.unwrap();
         assert_eq!(output_obj.clone().result.unwrap().key, result.key);
         assert_eq!(output_obj.result.unwrap().value, result.value)
     }
 
+    #[test]
+    fn test_serialize_payload_response_batch() {
+        let result = FakeParam {
+            key: String::from("testkey"),
+            value: String::from("testvalue"),
+        };
+
+        let err = RpcErrorBuilder::build(RpcError::InvalidRequest);
+        let response: RpcPayloadResponse<FakeParam> = RpcPayloadResponse::Batch(vec![
+            RpcResponse::with_success(Some(result), Some(RpcId::IntegerVal(1))),
+            RpcResponse::with_error(Some(err), None),
+        ]);
+
+        let jsonstr = serde_json::to_string(&response);
+        assert!(jsonstr.is_ok());
+        assert_eq!(
+            jsonstr.unwrap(),
+            r#"[{"jsonrpc":"2.0","result":{"key":"testkey","value":"testvalue"},"id":1},{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":null}]"#
+        )
+    }
+
     #[test]
     fn test_serialize_response_object_with_error() {
         let err = RpcErrorBuilder::build(RpcError::MethodNotFound);
         let response: RpcResponse<FakeParam> = RpcResponse::with_error(Some(err), None);
         let jsonstr = serde_json::to_string(&response);
-        assert!(!jsonstr.is_err());
+        assert!(jsonstr.is_ok());
         assert_eq!(
             jsonstr.unwrap(),
             r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":null}"#
@@ -0,0 +1,56 @@
+use rst_common::standard::serde::{self, Deserialize, Serialize};
+
+/// `SIGNATURE_KEY_ID_HEADER` is the header name used to send the id of the signing key
+pub const SIGNATURE_KEY_ID_HEADER: &str = "x-signature-key-id";
+
+/// `SIGNATURE_HEADER` is the header name used to send the hex encoded signature
+pub const SIGNATURE_HEADER: &str = "x-signature";
+
+/// `SIGNATURE_NONCE_HEADER` is the header name used to send the unique nonce of each request
+pub const SIGNATURE_NONCE_HEADER: &str = "x-signature-nonce";
+
+/// `SIGNATURE_TIMESTAMP_HEADER` is the header name used to send the signing time,
+/// in seconds since the unix epoch
+pub const SIGNATURE_TIMESTAMP_HEADER: &str = "x-signature-timestamp";
+
+/// `SigningAlgorithm` is the algorithm used to sign the request payloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "self::serde", rename_all = "kebab-case")]
+pub enum SigningAlgorithm {
+    HmacSha256,
+    Ed25519,
+}
+
+/// `signing_message` used to build the canonical message of a signed request
+///
+/// The message binds the key id, timestamp and nonce to the exact bytes of the request body,
+/// each of them separated by a new line. Both of the signer and the verifier must use it,
+/// so the signature will never depend on how the body is parsed
+pub fn signing_message(key_id: &str, timestamp: u64, nonce: &str, body: &[u8]) -> Vec<u8> {
+    let mut message = format!("{}\n{}\n{}\n", key_id, timestamp, nonce).into_bytes();
+    message.extend_from_slice(body);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rst_common::standard::serde_json;
+
+    #[test]
+    fn test_signing_message() {
+        let message = signing_message("agent-1", 1700000000, "abc", br#"{"id":1}"#);
+        assert_eq!(
+            String::from_utf8(message).unwrap(),
+            "agent-1\n1700000000\nabc\n{\"id\":1}"
+        );
+
+        let algorithm: SigningAlgorithm = serde_json::from_str(r#""hmac-sha256""#).unwrap();
+        assert_eq!(algorithm, SigningAlgorithm::HmacSha256);
+        assert_eq!(
+            serde_json::to_string(&SigningAlgorithm::Ed25519).unwrap(),
+            r#""ed25519""#
+        )
+    }
+}